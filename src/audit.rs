@@ -0,0 +1,336 @@
+use anyhow::Result;
+use cargo_metadata::{
+    MetadataCommand,
+    semver::{Version, VersionReq},
+};
+use colored::Colorize;
+use cvss::Cvss;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use toml::value::Datetime;
+
+use crate::{config::AuditConfig, error::PreflightError};
+
+#[derive(Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: Version,
+    source: Option<String>,
+}
+
+/// The TOML front matter of an advisory in the advisory database.
+#[derive(Deserialize)]
+struct Advisory {
+    advisory: AdvisoryMetadata,
+    #[serde(default)]
+    versions: AdvisoryVersions,
+}
+
+#[derive(Deserialize)]
+struct AdvisoryMetadata {
+    id: String,
+    /// Kind of informational advisory, such as `unmaintained` or `unsound`
+    informational: Option<String>,
+    cvss: Option<String>,
+    withdrawn: Option<Datetime>,
+}
+
+#[derive(Default, Deserialize)]
+struct AdvisoryVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+impl AdvisoryVersions {
+    fn affects(&self, version: &Version) -> bool {
+        !self
+            .patched
+            .iter()
+            .chain(&self.unaffected)
+            .filter_map(|req| VersionReq::parse(req).ok())
+            .any(|req| req.matches(version))
+    }
+}
+
+/// A date as `(year, month, day)` from days since 1970-01-01, from Howard Hinnant's `civil_from_days`.
+fn civil_from_days(days: u64) -> (u16, u8, u8) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (
+        u16::try_from(year).unwrap_or(u16::MAX),
+        u8::try_from(month).unwrap_or(12),
+        u8::try_from(day).unwrap_or(31),
+    )
+}
+
+/// Today's date in UTC, as `(year, month, day)`.
+fn today() -> (u16, u8, u8) {
+    civil_from_days(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() / 86_400),
+    )
+}
+
+/// Whether an ignore with the given expiry still applies today.
+fn ignore_applies(until: Option<&Datetime>) -> bool {
+    until
+        .and_then(|until| until.date)
+        .is_none_or(|date| (date.year, date.month, date.day) >= today())
+}
+
+/// Severity and base score of a CVSS v3 or v4 vector, such as `critical 9.8`.
+fn cvss_rating(vector: &str) -> Option<String> {
+    let cvss: Cvss = vector.parse().ok()?;
+    Some(format!("{} {:.1}", cvss.severity().as_str(), cvss.score()))
+}
+
+/// Parse an advisory file, returning its front matter and title.
+fn read_advisory(path: &Path) -> Option<(Advisory, String)> {
+    let contents = fs::read_to_string(path).ok()?;
+    let front_matter = contents.strip_prefix("```toml")?;
+    let (front_matter, body) = front_matter.split_once("```")?;
+    let advisory = toml::from_str(front_matter).ok()?;
+    let title = body
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .unwrap_or_default()
+        .to_owned();
+    Some((advisory, title))
+}
+
+/// The path of a crate in the registry index, such as `se/rd/serde`.
+fn index_path(name: &str) -> PathBuf {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => Path::new("1").join(&name),
+        2 => Path::new("2").join(&name),
+        3 => Path::new("3").join(&name[..1]).join(&name),
+        _ => Path::new(&name[..2]).join(&name[2..4]).join(&name),
+    }
+}
+
+/// Whether the local registry index cache marks a version as yanked, or `None` if it isn't cached.
+fn is_yanked(name: &str, version: &Version) -> Option<bool> {
+    let index = dirs::home_dir()?.join(".cargo/registry/index");
+    let version = version.to_string();
+    fs::read_dir(index).ok()?.flatten().find_map(|registry| {
+        let cache = fs::read(registry.path().join(".cache").join(index_path(name))).ok()?;
+        // Cache entries are NUL separated, with each version followed by its index JSON
+        cache
+            .split(|byte| *byte == 0)
+            .filter_map(|entry| serde_json::from_slice::<Value>(entry).ok())
+            .find(|entry| entry["vers"] == version.as_str())
+            .map(|entry| entry["yanked"] == true)
+    })
+}
+
+/// Advisories affecting a locked package, and whether it's yanked, each with whether it fails the check.
+fn package_findings(
+    cfg: &AuditConfig,
+    db_path: &Path,
+    package: &LockedPackage,
+) -> Vec<(String, bool)> {
+    let mut findings = Vec::new();
+    let advisories = fs::read_dir(db_path.join("crates").join(&package.name))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| read_advisory(&entry.path()));
+    for (advisory, title) in advisories {
+        let metadata = &advisory.advisory;
+        if metadata.withdrawn.is_some() || !advisory.versions.affects(&package.version) {
+            continue;
+        }
+        let ignore = cfg.ignore.iter().find(|ignore| ignore.id == metadata.id);
+        if ignore.is_some_and(|ignore| ignore_applies(ignore.until.as_ref())) {
+            continue;
+        }
+
+        let kind = metadata.informational.clone().unwrap_or_else(|| {
+            metadata
+                .cvss
+                .as_deref()
+                .and_then(cvss_rating)
+                .unwrap_or_else(|| "vulnerability".to_owned())
+        });
+        let mut finding = vec![format!(
+            "        - {} ({kind}) {} {}: {title}",
+            metadata.id.bold(),
+            package.name,
+            package.version
+        )];
+        if !advisory.versions.patched.is_empty() {
+            finding.push(format!(
+                "            Patched in: {}",
+                advisory.versions.patched.join(", ")
+            ));
+        }
+        if let Some(until) = ignore.and_then(|ignore| ignore.until.as_ref()) {
+            finding.push(format!("            Ignore expired after {until}"));
+        }
+        findings.push((
+            finding.join("\n"),
+            metadata.informational.is_none() || cfg.deny_informational,
+        ));
+    }
+
+    if is_yanked(&package.name, &package.version) == Some(true) {
+        findings.push((
+            format!("        - {} {} is yanked", package.name, package.version),
+            cfg.deny_yanked,
+        ));
+    }
+    findings
+}
+
+pub fn audit(cfg: &AuditConfig) -> Result<()> {
+    let db_path = cfg.db_path.clone().unwrap_or_else(|| {
+        dirs::home_dir()
+            .unwrap_or_default()
+            .join(".cargo/advisory-db")
+    });
+    if !db_path.join("crates").is_dir() {
+        return Err(PreflightError::AuditFailed {
+            audit_report: format!(
+                "No advisory database found at {}, clone it with `git clone https://github.com/rustsec/advisory-db {}`",
+                db_path.display(),
+                db_path.display()
+            ),
+        }
+        .into());
+    }
+
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let lockfile: Lockfile = toml::from_str(&fs::read_to_string(
+        metadata.workspace_root.join("Cargo.lock"),
+    )?)?;
+    let packages: Vec<&LockedPackage> = lockfile
+        .package
+        .iter()
+        .filter(|package| {
+            package.source.as_deref().is_some_and(|source| {
+                source.starts_with("registry+") || source.starts_with("sparse+")
+            })
+        })
+        .collect();
+
+    let mut denied = Vec::new();
+    let mut warnings = Vec::new();
+    for package in &packages {
+        for (finding, deny) in package_findings(cfg, &db_path, package) {
+            if deny {
+                denied.push(finding);
+            } else {
+                warnings.push(finding);
+            }
+        }
+    }
+
+    let mut report = Vec::new();
+    if !denied.is_empty() {
+        report.push(denied.join("\n"));
+    }
+    if !warnings.is_empty() {
+        report.push(format!(
+            "    {}\n{}",
+            "Warnings:".yellow(),
+            warnings.join("\n")
+        ));
+    }
+    if denied.is_empty() {
+        println!(
+            "    {}",
+            format!(
+                "[√] Audit preflight check passed ({} crates)",
+                packages.len()
+            )
+            .green()
+        );
+        if !report.is_empty() {
+            println!("{}", report.join("\n"));
+        }
+        Ok(())
+    } else {
+        Err(PreflightError::AuditFailed {
+            audit_report: report.join("\n"),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cvss_v3_rating() {
+        assert_eq!(
+            cvss_rating("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H").as_deref(),
+            Some("critical 9.8")
+        );
+        assert_eq!(
+            cvss_rating("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:H").as_deref(),
+            Some("high 7.5")
+        );
+        assert_eq!(
+            cvss_rating("CVSS:3.0/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N").as_deref(),
+            Some("medium 6.1")
+        );
+        assert_eq!(
+            cvss_rating("CVSS:3.1/AV:L/AC:H/PR:H/UI:R/S:U/C:L/I:N/A:N").as_deref(),
+            Some("low 1.8")
+        );
+        assert_eq!(
+            cvss_rating("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:N").as_deref(),
+            Some("none 0.0")
+        );
+    }
+
+    #[test]
+    fn cvss_v4_rating() {
+        assert_eq!(
+            cvss_rating("CVSS:4.0/AV:N/AC:L/AT:N/PR:N/UI:N/VC:H/VI:H/VA:H/SC:N/SI:N/SA:N")
+                .as_deref(),
+            Some("critical 9.3")
+        );
+    }
+
+    #[test]
+    fn invalid_cvss_has_no_rating() {
+        assert_eq!(
+            cvss_rating("CVSS:3.1/AV:Q/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"),
+            None
+        );
+        assert_eq!(cvss_rating("not a vector"), None);
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(20_088), (2024, 12, 31));
+    }
+}
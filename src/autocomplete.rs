@@ -19,6 +19,7 @@ trait BranchCompleter {
     fn update_input(&mut self, input: &str);
     fn get_branches(&self) -> &[String];
 
+    #[allow(clippy::unnecessary_sort_by)]
     fn fuzzy_sort(&self, input: &str) -> Vec<(String, i64)> {
         let mut matches: Vec<(String, i64)> = self
             .get_branches()
@@ -1,28 +1,344 @@
 use anyhow::Result;
-use cargo_shear::{CargoShear, cargo_shear_options};
+use cargo_metadata::MetadataCommand;
 use colored::Colorize;
-use ripsecrets::find_secrets;
-use std::{
-    env,
-    io::Read,
-    path::PathBuf,
-    process::{Command, ExitCode},
+use git2::Status;
+use std::{env, path::Path, process::Command};
+
+use crate::{
+    audit::audit,
+    config::{CheckOptions, DocConfig, PreflightConfig},
+    coverage::{coverage, coverage_command},
+    error::PreflightError,
+    features::{feature_commands, features},
+    file_size::file_size,
+    forbidden::forbidden_patterns,
+    git::{
+        get_current_branch_name, get_dirty_files, get_file_status, get_staged_files, tag_exists,
+    },
+    licenses::licenses,
+    package::{package, package_commands},
+    secrets::secrets,
+    semver::{describe_semver, semver},
+    shear::shear,
+    testing::{test, test_command, test_selections},
+    toolchain::{MatrixRun, matrix, matrix_cells, msrv, msrv_commands},
+    util::display_vecs,
+};
+
+/// Default arguments for a cargo-based check, split around the `--` separator.
+pub struct CargoArgs {
+    pub args: &'static [&'static str],
+    pub lint_args: &'static [&'static str],
+}
+
+const FMT_ARGS: CargoArgs = CargoArgs {
+    args: &["fmt"],
+    lint_args: &["--check"],
+};
+const CLIPPY_ARGS: CargoArgs = CargoArgs {
+    args: &["clippy"],
+    lint_args: &["-D", "warnings"],
+};
+const CHECK_TESTS_ARGS: CargoArgs = CargoArgs {
+    args: &["check", "--tests"],
+    lint_args: &[],
+};
+const CHECK_EXAMPLES_ARGS: CargoArgs = CargoArgs {
+    args: &["check", "--examples"],
+    lint_args: &[],
+};
+const CHECK_BENCHES_ARGS: CargoArgs = CargoArgs {
+    args: &["check", "--benches"],
+    lint_args: &[],
+};
+pub const TEST_ARGS: CargoArgs = CargoArgs {
+    args: &["test", "--no-fail-fast"],
+    lint_args: &[],
+};
+
+pub const FEATURES_ARGS: CargoArgs = CargoArgs {
+    args: &["check"],
+    lint_args: &[],
+};
+
+pub const SEMVER_ARGS: CargoArgs = CargoArgs {
+    args: &["semver-checks", "check-release"],
+    lint_args: &[],
 };
-use termcolor::{BufferWriter, ColorChoice};
 
-use crate::{error::PreflightError, git::get_current_branch_name};
+const PUBLISH_DRY_RUN_ARGS: CargoArgs = CargoArgs {
+    args: &["publish", "--dry-run"],
+    lint_args: &[],
+};
+
+pub const PACKAGE_ARGS: CargoArgs = CargoArgs {
+    args: &["package", "--list", "--allow-dirty", "--offline"],
+    lint_args: &[],
+};
+
+const DOC_ARGS: CargoArgs = CargoArgs {
+    args: &["doc", "--no-deps"],
+    lint_args: &[],
+};
+const DOCTEST_ARGS: CargoArgs = CargoArgs {
+    args: &["test", "--doc"],
+    lint_args: &[],
+};
+
+pub const COVERAGE_ARGS: CargoArgs = CargoArgs {
+    args: &["test", "--tests"],
+    lint_args: &[],
+};
+
+pub const MSRV_ARGS: CargoArgs = CargoArgs {
+    args: &["check"],
+    lint_args: &[],
+};
+
+/// Build a cargo command from a check's default arguments, its configured options and any `extra_args`.
+pub fn cargo_command(
+    defaults: &CargoArgs,
+    options: &CheckOptions,
+    extra_args: &[String],
+) -> Command {
+    let mut cmd = Command::new("cargo");
+    if let Some(toolchain) = &options.toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.args(defaults.args)
+        .args(extra_args)
+        .args(&options.args)
+        .envs(&options.env);
+    // `cargo fmt` doesn't build anything, so has no `--target`
+    if let (Some(target), false) = (&options.target, defaults.args == FMT_ARGS.args) {
+        cmd.args(["--target", target]);
+    }
+    let mut lint_args: Vec<String> = options.lint_args.as_ref().map_or_else(
+        || defaults.lint_args.iter().map(ToString::to_string).collect(),
+        Clone::clone,
+    );
+    // Without `--check`, `cargo fmt` would rewrite files instead of checking them
+    if defaults.lint_args.contains(&"--check") && !lint_args.iter().any(|arg| arg == "--check") {
+        lint_args.insert(0, "--check".to_owned());
+    }
+    if !lint_args.is_empty() {
+        cmd.arg("--").args(lint_args);
+    }
+    cmd
+}
+
+/// Default arguments for a cargo-based check, or `None` if the check runs in-process.
+pub fn cargo_args(check: &str) -> Result<Option<CargoArgs>> {
+    Ok(Some(match check {
+        "fmt" => FMT_ARGS,
+        "clippy" => CLIPPY_ARGS,
+        "check_tests" => CHECK_TESTS_ARGS,
+        "check_examples" => CHECK_EXAMPLES_ARGS,
+        "check_benches" => CHECK_BENCHES_ARGS,
+        "test" => TEST_ARGS,
+        "features" => FEATURES_ARGS,
+        "semver" => SEMVER_ARGS,
+        "publish_dry_run" => PUBLISH_DRY_RUN_ARGS,
+        "package" => PACKAGE_ARGS,
+        "doc" => DOC_ARGS,
+        "doctest" => DOCTEST_ARGS,
+        "msrv" => MSRV_ARGS,
+        "coverage" => COVERAGE_ARGS,
+        "unused_deps" | "secrets" | "clean_tree" | "version_tag" | "licenses" | "audit"
+        | "lockfile" | "forbidden_patterns" | "file_size" => {
+            return Ok(None);
+        }
+        _ => {
+            return Err(PreflightError::InvalidCheck {
+                config: check.to_owned(),
+            }
+            .into());
+        }
+    }))
+}
+
+/// Format a command as it would be run, with its working directory and environment.
+pub fn describe_command(cmd: &Command) -> Result<String> {
+    let command = std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let envs = cmd
+        .get_envs()
+        .map(|(key, value)| {
+            format!(
+                "{}={}",
+                key.to_string_lossy(),
+                value
+                    .map(|value| value.to_string_lossy())
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    let cwd = cmd
+        .get_current_dir()
+        .map_or_else(env::current_dir, |cwd| Ok(cwd.to_owned()))?;
+
+    Ok(format!(
+        "`{command}`\n        cwd: {}\n        env: {}",
+        cwd.display(),
+        if envs.is_empty() {
+            "(inherited)".to_owned()
+        } else {
+            envs.join(" ")
+        }
+    ))
+}
 
-pub fn run_checks(checks: &[String]) -> Result<()> {
+/// Describe what a check would do, without running it.
+pub fn describe_check(check: &str, cfg: &PreflightConfig, hook: &str) -> Result<String> {
+    let options = cfg.check_options(check);
+    if let Some(runs) = matrix_runs(check, cfg, &options, hook)? {
+        return Ok(runs
+            .iter()
+            .flat_map(|(_, commands)| commands)
+            .map(describe_command)
+            .collect::<Result<Vec<_>>>()?
+            .join("\n      "));
+    }
+    if check == "msrv" {
+        return Ok(msrv_commands(&options)?
+            .into_iter()
+            .map(|(label, cmd)| match cmd {
+                Ok(cmd) => describe_command(&cmd),
+                Err(version) => Ok(format!(
+                    "{label}: no toolchain installed for rust-version {version}"
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join("\n      "));
+    }
+    let commands = match (check, cargo_args(check)?) {
+        ("features", _) => feature_commands(&cfg.features, &options)?
+            .into_iter()
+            .map(|(_, cmd)| cmd)
+            .collect(),
+        ("semver", _) => vec![describe_semver(&cfg.semver, &options)],
+        ("doc", _) => vec![doc_command(&cfg.doc, &options)],
+        ("coverage", _) => vec![coverage_command(
+            &options,
+            Path::new("<temporary directory>"),
+        )?],
+        ("test", _) => test_selections(&cfg.test, hook)?
+            .iter()
+            .map(|selection| test_command(&cfg.test, &options, selection))
+            .collect(),
+        ("package", _) => package_commands(&options)?
+            .into_iter()
+            .map(|(_, cmd)| cmd)
+            .collect(),
+        (_, Some(defaults)) => vec![cargo_command(&defaults, &options, &[])],
+        (_, None) => {
+            let runner = match check {
+                "unused_deps" => "cargo-shear",
+                "secrets" => "ripsecrets",
+                "clean_tree" => "git status",
+                "licenses" => "cargo metadata --offline",
+                "audit" => "Cargo.lock and local advisory-db lookup",
+                "lockfile" => "git status and `cargo metadata --locked --offline`",
+                "forbidden_patterns" => "pattern search of staged changes",
+                "file_size" if hook == "push" => "size lookup of pushed git blobs",
+                "file_size" => "size lookup of staged git blobs",
+                _ => "git tag lookup",
+            };
+            return Ok(format!(
+                "{runner} (in-process)\n        cwd: {}",
+                env::current_dir()?.display()
+            ));
+        }
+    };
+
+    Ok(commands
+        .iter()
+        .map(describe_command)
+        .collect::<Result<Vec<_>>>()?
+        .join("\n      "))
+}
+
+/// The options and commands for each run of `check` in its toolchain and target matrix, or `None` if the check has
+/// no matrix configured or doesn't support one.
+///
+/// Tests run once per cell for each selection from `test_selections`.
+fn matrix_runs(
+    check: &str,
+    cfg: &PreflightConfig,
+    options: &CheckOptions,
+    hook: &str,
+) -> Result<Option<Vec<MatrixRun>>> {
+    if !options.has_matrix() {
+        return Ok(None);
+    }
+    let defaults = match check {
+        "clippy" => Some(CLIPPY_ARGS),
+        "check_tests" => Some(CHECK_TESTS_ARGS),
+        "check_examples" => Some(CHECK_EXAMPLES_ARGS),
+        "check_benches" => Some(CHECK_BENCHES_ARGS),
+        "doctest" => Some(DOCTEST_ARGS),
+        "doc" | "test" => None,
+        _ => return Ok(None),
+    };
+    let command = |cell: &CheckOptions, selection: &[String]| match (check, &defaults) {
+        (_, Some(defaults)) => cargo_command(defaults, cell, &[]),
+        ("doc", None) => doc_command(&cfg.doc, cell),
+        (_, None) => test_command(&cfg.test, cell, selection),
+    };
+    let selections = if check == "test" {
+        test_selections(&cfg.test, hook)?
+    } else {
+        vec![vec![]]
+    };
+
+    Ok(Some(
+        matrix_cells(options)
+            .into_iter()
+            .map(|cell| {
+                let commands = selections
+                    .iter()
+                    .map(|selection| command(&cell, selection))
+                    .collect();
+                (cell, commands)
+            })
+            .collect(),
+    ))
+}
+
+pub fn run_checks(checks: &[String], cfg: &PreflightConfig, hook: &str) -> Result<()> {
     for check in checks {
+        let options = cfg.check_options(check);
+        if let Some(runs) = matrix_runs(check, cfg, &options, hook)? {
+            matrix(check, runs)?;
+            continue;
+        }
         match check.as_str() {
-            "fmt" => cargo_fmt(),
-            "clippy" => cargo_clippy(),
-            "check_tests" => cargo_check_tests(),
-            "check_examples" => cargo_check_examples(),
-            "check_benches" => cargo_check_benches(),
-            "test" => cargo_test(),
-            "unused_deps" => shear(),
-            "secrets" => secrets(),
+            "fmt" => cargo_fmt(&options),
+            "clippy" => cargo_clippy(&options),
+            "check_tests" => cargo_check_tests(&options),
+            "check_examples" => cargo_check_examples(&options),
+            "check_benches" => cargo_check_benches(&options),
+            "test" => test(&cfg.test, &options, hook),
+            "doc" => cargo_doc(&cfg.doc, &options),
+            "doctest" => cargo_doctest(&options),
+            "msrv" => msrv(&options),
+            "coverage" => coverage(&cfg.coverage, &options),
+            "features" => features(&cfg.features, &options),
+            "semver" => semver(&cfg.semver, &options),
+            "publish_dry_run" => publish_dry_run(&options),
+            "package" => package(&cfg.package, &cfg.secrets, &options),
+            "unused_deps" => shear(&cfg.shear),
+            "secrets" => secrets(&cfg.secrets, hook),
+            "clean_tree" => clean_tree(),
+            "version_tag" => version_tag(),
+            "licenses" => licenses(&cfg.licenses),
+            "audit" => audit(&cfg.audit),
+            "lockfile" => lockfile(),
+            "forbidden_patterns" => forbidden_patterns(&cfg.forbidden_patterns),
+            "file_size" => file_size(&cfg.file_size, hook),
             _ => Err(PreflightError::InvalidCheck {
                 config: check.to_owned(),
             }
@@ -32,10 +348,8 @@ pub fn run_checks(checks: &[String]) -> Result<()> {
     Ok(())
 }
 
-pub fn cargo_fmt() -> Result<()> {
-    let output = Command::new("cargo")
-        .args(["fmt", "--", "--check"])
-        .output()?;
+pub fn cargo_fmt(options: &CheckOptions) -> Result<()> {
+    let output = cargo_command(&FMT_ARGS, options, &[]).output()?;
 
     if output.status.success() {
         println!("    {}", "[√] Formatting preflight check passed".green());
@@ -49,10 +363,8 @@ pub fn cargo_fmt() -> Result<()> {
     }
 }
 
-pub fn cargo_clippy() -> Result<()> {
-    let output = Command::new("cargo")
-        .args(["clippy", "--", "-D", "warnings"])
-        .output()?;
+pub fn cargo_clippy(options: &CheckOptions) -> Result<()> {
+    let output = cargo_command(&CLIPPY_ARGS, options, &[]).output()?;
 
     if output.status.success() {
         println!("    {}", "[√] Clippy preflight check passed".green());
@@ -65,8 +377,8 @@ pub fn cargo_clippy() -> Result<()> {
     }
 }
 
-pub fn cargo_check_tests() -> Result<()> {
-    let output = Command::new("cargo").args(["check", "--tests"]).output()?;
+pub fn cargo_check_tests(options: &CheckOptions) -> Result<()> {
+    let output = cargo_command(&CHECK_TESTS_ARGS, options, &[]).output()?;
 
     if output.status.success() {
         println!("{}", "    [√] Check tests preflight check passed".green());
@@ -79,10 +391,8 @@ pub fn cargo_check_tests() -> Result<()> {
     }
 }
 
-pub fn cargo_check_examples() -> Result<()> {
-    let output = Command::new("cargo")
-        .args(["check", "--examples"])
-        .output()?;
+pub fn cargo_check_examples(options: &CheckOptions) -> Result<()> {
+    let output = cargo_command(&CHECK_EXAMPLES_ARGS, options, &[]).output()?;
 
     if output.status.success() {
         println!(
@@ -98,10 +408,8 @@ pub fn cargo_check_examples() -> Result<()> {
     }
 }
 
-pub fn cargo_check_benches() -> Result<()> {
-    let output = Command::new("cargo")
-        .args(["check", "--benches"])
-        .output()?;
+pub fn cargo_check_benches(options: &CheckOptions) -> Result<()> {
+    let output = cargo_command(&CHECK_BENCHES_ARGS, options, &[]).output()?;
 
     if output.status.success() {
         println!("{}", "    [√] Check benches preflight check passed".green());
@@ -114,54 +422,53 @@ pub fn cargo_check_benches() -> Result<()> {
     }
 }
 
-pub fn cargo_test() -> Result<()> {
-    let output = Command::new("cargo").arg("test").output()?;
+/// `cargo doc` with rustdoc warnings denied, unless `RUSTDOCFLAGS` is set in the check's options.
+pub fn doc_command(cfg: &DocConfig, options: &CheckOptions) -> Command {
+    let mut cmd = cargo_command(&DOC_ARGS, options, &[]);
+    if !options.env.contains_key("RUSTDOCFLAGS") {
+        let flags = if cfg.missing_docs {
+            "-D warnings -D missing_docs"
+        } else {
+            "-D warnings"
+        };
+        cmd.env("RUSTDOCFLAGS", flags);
+    }
+    cmd
+}
+
+pub fn cargo_doc(cfg: &DocConfig, options: &CheckOptions) -> Result<()> {
+    let output = doc_command(cfg, options).output()?;
 
     if output.status.success() {
-        println!("{}", "    [√] Tests preflight check passed".green());
+        println!("{}", "    [√] Docs preflight check passed".green());
         Ok(())
     } else {
-        Err(PreflightError::TestsFailed {
-            test_outputs: String::from_utf8_lossy(&output.stdout).to_string(),
+        Err(PreflightError::DocFailed {
+            doc_output: String::from_utf8_lossy(&output.stderr).to_string(),
         }
         .into())
     }
 }
 
-pub fn shear() -> Result<()> {
-    let options = cargo_shear_options()
-        .run_inner([env::current_dir()?.as_os_str()].as_slice())
-        .map_err(|_| anyhow::anyhow!("Parse failure"))?;
-    let mut buf = gag::BufferRedirect::stdout()?;
-    let exit_code = CargoShear::new(options).run();
-    let mut output = String::new();
-    buf.read_to_string(&mut output)?;
-    drop(buf);
+pub fn cargo_doctest(options: &CheckOptions) -> Result<()> {
+    let output = cargo_command(&DOCTEST_ARGS, options, &[]).output()?;
 
-    match exit_code {
-        code if code == ExitCode::from(0) => {
-            println!(
-                "{}",
-                "    [√] Unused dependencies preflight check passed".green()
-            );
-            Ok(())
-        }
-        code if code == ExitCode::from(1) => Err(PreflightError::ShearFailed {
-            shear_output: output,
-        }
-        .into()),
-        _ => Err(PreflightError::ShearFailed {
-            shear_output: "Processing error during shear...".to_owned(),
+    if output.status.success() {
+        println!("{}", "    [√] Doctests preflight check passed".green());
+        Ok(())
+    } else {
+        Err(PreflightError::DoctestFailed {
+            doctest_outputs: String::from_utf8_lossy(&output.stdout).to_string(),
         }
-        .into()),
+        .into())
     }
 }
 
-pub fn check_branch_rules(branches: &[String]) -> bool {
+pub fn check_branch_rules(branches: &[String], branch: Option<&str>) -> bool {
     if branches.is_empty() {
         return true;
     }
-    get_current_branch_name().map_or_else(|| {
+    branch.map(ToOwned::to_owned).or_else(get_current_branch_name).map_or_else(|| {
         println!(
             "{}",
             "It looks like you're not on a git branch... Preflight will continue, but there may be an error later".italic()
@@ -170,33 +477,150 @@ pub fn check_branch_rules(branches: &[String]) -> bool {
     }, |branch| branches.contains(&branch))
 }
 
-pub fn secrets() -> Result<()> {
-    let mut buf = gag::BufferRedirect::stdout()?;
-    let mut output = String::new();
+pub fn publish_dry_run(options: &CheckOptions) -> Result<()> {
+    let output = cargo_command(&PUBLISH_DRY_RUN_ARGS, options, &[]).output()?;
 
-    let ret = find_secrets(
-        &[PathBuf::from(".")],
-        &[],
-        false,
-        false,
-        BufferWriter::stdout(ColorChoice::Never),
-    );
+    if output.status.success() {
+        println!(
+            "    {}",
+            "[√] Publish dry run preflight check passed".green()
+        );
+        Ok(())
+    } else {
+        Err(PreflightError::PublishDryRunFailed {
+            publish_output: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+        .into())
+    }
+}
+
+pub fn clean_tree() -> Result<()> {
+    let dirty = get_dirty_files()?;
 
-    buf.read_to_string(&mut output)?;
-    drop(buf);
+    if dirty.is_empty() {
+        println!("    {}", "[√] Clean tree preflight check passed".green());
+        Ok(())
+    } else {
+        Err(PreflightError::CleanTreeFailed {
+            dirty_files: display_vecs(&dirty),
+        }
+        .into())
+    }
+}
+
+pub fn version_tag() -> Result<()> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let missing: Vec<String> = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter(|package| {
+            package
+                .publish
+                .as_ref()
+                .is_none_or(|registries| !registries.is_empty())
+        })
+        .filter_map(|package| {
+            let version = &package.version;
+            let tags = [
+                format!("v{version}"),
+                version.to_string(),
+                format!("{}-v{version}", package.name),
+            ];
+            (!tags.iter().any(|tag| tag_exists(tag))).then(|| {
+                format!(
+                    "{} {version}, expected one of: {}",
+                    package.name,
+                    tags.join(", ")
+                )
+            })
+        })
+        .collect();
 
-    match ret {
-        Ok(0) => {
-            println!("    {}", "[√] Secrets preflight check passed".green());
-            Ok(())
+    if missing.is_empty() {
+        println!("    {}", "[√] Version tag preflight check passed".green());
+        Ok(())
+    } else {
+        Err(PreflightError::VersionTagFailed {
+            missing_tags: display_vecs(&missing),
         }
-        Ok(num) => Err(PreflightError::SecretsFailed {
-            ripsecrets_output: format!("Found {num} secret(s): \n{output}"),
+        .into())
+    }
+}
+
+pub fn lockfile() -> Result<()> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let lockfile = metadata
+        .workspace_root
+        .join("Cargo.lock")
+        .into_std_path_buf();
+    let mut issues = Vec::new();
+
+    let status = get_file_status(&lockfile).unwrap_or(Status::WT_NEW);
+    if status.intersects(Status::WT_NEW | Status::IGNORED) {
+        issues.push("Cargo.lock is not committed".to_owned());
+    }
+
+    let output = Command::new("cargo")
+        .args(["metadata", "--locked", "--offline", "--format-version", "1"])
+        .output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Cargo says the lockfile "needs to be updated but --locked was passed", or in newer versions that it "cannot
+        // update the lock file ... because --locked was passed". Other errors, such as an invalid manifest, are shown
+        // as they are
+        if stderr.contains("--locked was passed") {
+            issues.push(format!(
+                "Cargo.lock is out of date with Cargo.toml:\n{}",
+                stderr.trim()
+            ));
+        } else {
+            issues.push(format!("`cargo metadata` failed:\n{}", stderr.trim()));
         }
-        .into()),
-        Err(err) => Err(PreflightError::SecretsFailed {
-            ripsecrets_output: err.to_string(),
+    }
+
+    let staged_manifests: Vec<String> = get_staged_files()?
+        .iter()
+        .filter(|path| path.file_name().is_some_and(|name| name == "Cargo.toml"))
+        .map(|path| {
+            path.strip_prefix(&metadata.workspace_root)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        })
+        .collect();
+    // Manifest and lockfile changes should be staged together
+    if !staged_manifests.is_empty() && status.contains(Status::WT_MODIFIED) {
+        issues.push(format!(
+            "Cargo.lock has unstaged changes, but these manifests are staged: {}",
+            staged_manifests.join(", ")
+        ));
+    }
+    if status.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED) {
+        let unstaged_manifests: Vec<String> = get_dirty_files()?
+            .into_iter()
+            .filter(|path| {
+                Path::new(path)
+                    .file_name()
+                    .is_some_and(|name| name == "Cargo.toml")
+                    && get_file_status(Path::new(path))
+                        .is_ok_and(|status| status.contains(Status::WT_MODIFIED))
+            })
+            .collect();
+        if !unstaged_manifests.is_empty() {
+            issues.push(format!(
+                "Cargo.lock is staged, but these manifests have unstaged changes: {}",
+                unstaged_manifests.join(", ")
+            ));
         }
-        .into()),
+    }
+
+    if issues.is_empty() {
+        println!("    {}", "[√] Lockfile preflight check passed".green());
+        Ok(())
+    } else {
+        Err(PreflightError::LockfileFailed {
+            lockfile_issues: display_vecs(&issues),
+        }
+        .into())
     }
 }
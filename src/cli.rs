@@ -1,7 +1,12 @@
 use anyhow::Result;
 use colored::Colorize;
-use inquire::{Confirm, MultiSelect, Select, Text};
-use std::fs::exists;
+use inquire::{Confirm, MultiSelect, Select, Text, validator::Validation};
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{File, exists},
+    io::{IsTerminal, stdin},
+};
 use tabled::{
     Table,
     settings::{Reverse, Rotate, Style},
@@ -9,9 +14,13 @@ use tabled::{
 
 use crate::{
     autocomplete::{GlobalBranchCompleter, LocalBranchCompleter},
-    config::{PreflightConfig, PreflightConfigWrapper, check_local_config},
-    fix::{autofix, over_ride},
-    preflight::preflight_checks,
+    checks::cargo_args,
+    config::{
+        CheckOptions, OnFailure, PreflightConfig, PreflightConfigWrapper, check_local_config,
+    },
+    error::PreflightError,
+    fix::{apply_autofix, over_ride},
+    preflight::RunOptions,
 };
 
 const CLAP_STYLING: clap::builder::styling::Styles = clap::builder::styling::Styles::styled()
@@ -23,6 +32,33 @@ const CLAP_STYLING: clap::builder::styling::Styles = clap::builder::styling::Sty
     .valid(clap_cargo::style::VALID)
     .invalid(clap_cargo::style::INVALID);
 
+/// Checks offered by the configuration wizard, and accepted by `run --check`.
+const CHECKS: [&str; 23] = [
+    "fmt",
+    "clippy",
+    "test",
+    "coverage",
+    "unused_deps",
+    "secrets",
+    "check_tests",
+    "check_examples",
+    "check_benches",
+    "doc",
+    "doctest",
+    "features",
+    "msrv",
+    "semver",
+    "publish_dry_run",
+    "package",
+    "clean_tree",
+    "version_tag",
+    "licenses",
+    "audit",
+    "lockfile",
+    "forbidden_patterns",
+    "file_size",
+];
+
 #[allow(clippy::cognitive_complexity)]
 pub fn parse_args<I: Iterator<Item = String>>(args: I) -> clap::ArgMatches {
     let cmd = clap::Command::new("cargo-preflight")
@@ -31,11 +67,31 @@ pub fn parse_args<I: Iterator<Item = String>>(args: I) -> clap::ArgMatches {
         .arg(clap::arg!(--"ground" "Un-initialise preflight in the current repository. This will remove all git hooks").value_parser(clap::value_parser!(bool)))
         .arg(clap::Arg::new("REMOTE").hide(true))
         .arg(clap::arg!(--"config" "Configure preflight checks to run").value_parser(clap::value_parser!(bool)))
-        .arg(clap::arg!(--"checklist" "Output the current configuration that will be applied in this repository").value_parser(clap::value_parser!(bool)));
+        .arg(clap::arg!(--"checklist" "Output the current configuration that will be applied in this repository").value_parser(clap::value_parser!(bool)))
+        .arg(clap::arg!(--"dry-run" "Print the checks and exact commands that would run, without executing anything").global(true))
+        .arg(clap::arg!(--"yes" "Answer yes to autofix and override prompts, where enabled in config").global(true))
+        .arg(clap::arg!(--"no-input" "Never prompt, and apply the `on_failure` policy from config to failed checks").global(true).conflicts_with("yes"))
+        .subcommand(
+            clap::Command::new("run")
+                .about("Run preflight checks on demand, optionally simulating a git hook")
+                .arg(clap::arg!(--"hook" <HOOK> "Simulate the given git hook, only running config entries with a matching `run_when`").value_parser(["commit", "push", "publish"]))
+                .arg(clap::arg!(--"check" <CHECK> "Only run the given check (can be repeated)").action(clap::ArgAction::Append).value_parser(CHECKS))
+                .arg(clap::arg!(--"entry" <ENTRY> "Only run the config entry with the given name, or index (starting at 0)"))
+                .arg(clap::arg!(--"branch" <BRANCH> "Apply branch rules as if the given branch was checked out")),
+        )
+        .subcommand(
+            clap::Command::new("publish")
+                .about("Run checks configured for `publish`, then `cargo publish` with the given arguments")
+                .arg(clap::Arg::new("ARGS").help("Arguments for `cargo publish`, use `--` before any that clash with preflight's own flags").num_args(0..).trailing_var_arg(true).allow_hyphen_values(true)),
+        )
+        .subcommand(
+            clap::Command::new("secrets-baseline")
+                .about("Record the secrets found now in the `baseline` file set under `[preflight.secrets]`, so only new ones fail the check"),
+        );
     cmd.get_matches_from(args)
 }
 
-pub fn autofix_prompt(cfg: &PreflightConfig, index: usize) -> Result<()> {
+pub fn autofix_prompt(cfg: &PreflightConfig, opts: &RunOptions, index: usize) -> Result<()> {
     let ans = Confirm::new(&format!(
         "Do you want to automatically apply {} suggestions?",
         &cfg.checks[index]
@@ -44,27 +100,31 @@ pub fn autofix_prompt(cfg: &PreflightConfig, index: usize) -> Result<()> {
     .with_help_message(
         "WARNING: This will apply changes to your dirty workspace, and may be potentially destructive.\nNo will end and fail preflight checks, yes will apply suggestions and continue",
     )
-    .prompt();
-
-    match ans {
-        Ok(false) => {
-            if cfg.over_ride {
-                over_ride(cfg, index)
-            } else {
-                Ok(())
-            }
-        }
-        Ok(true) => {
-            autofix(&cfg.checks[index])?;
-            preflight_checks(cfg, index)
-        }
-        Err(_) => {
-            println!("Error autofixing preflight");
-            Ok(())
+    .prompt()
+    .map_err(|err| PreflightError::PromptFailed {
+        prompt_error: err.to_string(),
+    })?;
+
+    if ans {
+        apply_autofix(cfg, opts, index)
+    } else if cfg.over_ride {
+        over_ride(cfg, opts, index)
+    } else {
+        Err(PreflightError::CheckFailed {
+            failed_check: cfg.checks[index].clone(),
         }
+        .into())
     }
 }
 
+/// Whether Preflight can prompt: not running in CI, and with a terminal to read answers from.
+///
+/// Git redirects stdin for some hooks (such as `pre-push`), so the controlling terminal is checked too.
+pub fn is_interactive() -> bool {
+    let ci = env::var("CI").is_ok_and(|ci| !ci.is_empty() && ci != "false" && ci != "0");
+    !ci && (stdin().is_terminal() || File::open("/dev/tty").is_ok())
+}
+
 pub fn update_config() -> Result<()> {
     let mut preflight_configs = Vec::new();
 
@@ -77,22 +137,14 @@ pub fn update_config() -> Result<()> {
     .prompt()?;
 
     loop {
-        let checks = vec![
-            "fmt",
-            "clippy",
-            "test",
-            "unused_deps",
-            "secrets",
-            "check_tests",
-            "check_examples",
-            "check_benches",
-        ];
-        let run_when = vec!["commit", "push"];
-
-        let chosen_checks = MultiSelect::new("Select checks to run:", checks)
+        let run_when = vec!["commit", "push", "publish"];
+
+        let chosen_checks = MultiSelect::new("Select checks to run:", CHECKS.to_vec())
             .with_vim_mode(true)
             .prompt()?;
 
+        let options = prompt_check_options(&chosen_checks)?;
+
         let chosen_run_when = MultiSelect::new("Select when to run checks:", run_when)
             .with_vim_mode(true)
             .prompt()?;
@@ -109,6 +161,10 @@ pub fn update_config() -> Result<()> {
                 .prompt()
         }?;
 
+        let name = Text::new("Name this configuration:")
+            .with_help_message("Optional, used to select it with `cargo preflight run --entry`")
+            .prompt()?;
+
         let autofix = Confirm::new("Enable autofix functionality?")
             .with_default(false)
             .with_help_message(
@@ -121,12 +177,24 @@ pub fn update_config() -> Result<()> {
             .with_help_message("This will allow you to override Preflight on failed checks")
             .prompt()?;
 
+        let on_failure = Select::new(
+            "What should happen to failed checks when Preflight can't prompt?",
+            vec![OnFailure::Fail, OnFailure::Autofix, OnFailure::Override],
+        )
+        .with_vim_mode(true)
+        .with_help_message("Used in CI, GUI git clients, or with `--no-input`")
+        .prompt()?;
+
         let cfg = PreflightConfig {
+            name: Some(name).filter(|name| !name.trim().is_empty()),
             run_when: chosen_run_when.into_iter().map(ToOwned::to_owned).collect(),
             branches: branches.split_whitespace().map(ToOwned::to_owned).collect(),
             checks: chosen_checks.into_iter().map(ToOwned::to_owned).collect(),
             autofix,
             over_ride,
+            on_failure,
+            options,
+            ..Default::default()
         };
 
         preflight_configs.push(cfg);
@@ -156,6 +224,77 @@ pub fn update_config() -> Result<()> {
     Ok(())
 }
 
+/// Ask for extra arguments for each chosen cargo-based check, skipping checks left blank.
+fn prompt_check_options(checks: &[&str]) -> Result<BTreeMap<String, CheckOptions>> {
+    let mut options = BTreeMap::new();
+    for &check in checks {
+        if cargo_args(check)?.is_none() {
+            continue;
+        }
+        let args = Text::new(&format!("Extra cargo arguments for {check}:"))
+            .with_help_message("e.g. `--workspace --all-features`, leave blank for none")
+            .prompt()?;
+        let lint_args = if check == "clippy" {
+            Some(
+                Text::new("Lint arguments for clippy:")
+                    .with_default("-D warnings")
+                    .with_help_message("Passed to clippy after `--`")
+                    .prompt()?,
+            )
+            .filter(|lint_args| lint_args != "-D warnings")
+        } else {
+            None
+        };
+
+        let env = Text::new(&format!("Environment variables for {check}:"))
+            .with_help_message("e.g. `RUSTFLAGS=-Dwarnings`, leave blank for none")
+            .with_validator(|input: &str| {
+                Ok(if input.split_whitespace().all(|var| var.contains('=')) {
+                    Validation::Valid
+                } else {
+                    Validation::Invalid("Use space separated `KEY=VALUE` pairs".into())
+                })
+            })
+            .prompt()?;
+        // `cargo fmt` doesn't build anything, so has no `--target`
+        let target = if check == "fmt" {
+            String::new()
+        } else {
+            Text::new(&format!("Target triple for {check}:"))
+                .with_help_message("e.g. `wasm32-unknown-unknown`, leave blank for the host")
+                .prompt()?
+        };
+
+        if args.trim().is_empty()
+            && lint_args.is_none()
+            && env.trim().is_empty()
+            && target.trim().is_empty()
+        {
+            continue;
+        }
+        options.insert(
+            check.to_owned(),
+            CheckOptions {
+                args: args.split_whitespace().map(ToOwned::to_owned).collect(),
+                lint_args: lint_args.map(|lint_args| {
+                    lint_args
+                        .split_whitespace()
+                        .map(ToOwned::to_owned)
+                        .collect()
+                }),
+                env: env
+                    .split_whitespace()
+                    .filter_map(|var| var.split_once('='))
+                    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                    .collect(),
+                target: Some(target.trim().to_owned()).filter(|target| !target.is_empty()),
+                ..Default::default()
+            },
+        );
+    }
+    Ok(options)
+}
+
 pub fn print_checklist() -> Result<()> {
     let cfg = check_local_config()?;
     let mut table = Table::new(cfg.preflight);
@@ -1,12 +1,16 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::fs::exists;
+use std::{collections::BTreeMap, fmt, fs::exists, path::PathBuf};
 use tabled::Tabled;
+use toml::value::Datetime;
 
-use crate::util::{display_checks, display_vecs};
+use crate::util::{display_checks, display_name, display_options, display_vecs};
 
-#[derive(Debug, Serialize, Deserialize, Tabled)]
+#[derive(Debug, Clone, Serialize, Deserialize, Tabled)]
 pub struct PreflightConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(display = "display_name")]
+    pub name: Option<String>,
     #[tabled(display = "display_vecs")]
     pub run_when: Vec<String>,
     #[tabled(display = "display_vecs")]
@@ -16,16 +20,476 @@ pub struct PreflightConfig {
     pub autofix: bool,
     #[tabled(rename = "override")]
     pub over_ride: bool,
+    #[serde(default)]
+    pub on_failure: OnFailure,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[tabled(display = "display_options")]
+    pub options: BTreeMap<String, CheckOptions>,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub features: FeaturesConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub semver: SemverConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub package: PackageConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub doc: DocConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub test: TestConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub coverage: CoverageConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub licenses: LicensesConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub shear: ShearConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub secrets: SecretsConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub forbidden_patterns: ForbiddenPatternsConfig,
+    #[serde(default)]
+    #[tabled(skip)]
+    pub file_size: FileSizeConfig,
+}
+
+impl PreflightConfig {
+    /// Options configured for `check`, or the defaults if there are none.
+    pub fn check_options(&self, check: &str) -> CheckOptions {
+        self.options.get(check).cloned().unwrap_or_default()
+    }
+}
+
+/// Per-check options for cargo-based checks, set under `[preflight.options.<check>]`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckOptions {
+    /// Extra cargo arguments, such as `--workspace` or `--all-features`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    /// Arguments after `--`, replacing the check's defaults (such as `-D warnings` for clippy), though fmt always keeps `--check`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lint_args: Option<Vec<String>>,
+    /// Environment variables to set for the check
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Target triple to build for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Rustup toolchain to run the check with, such as `nightly`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<String>,
+    /// Toolchains to run the check with, one run per toolchain and target
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub toolchains: Vec<String>,
+    /// Target triples to build for, one run per toolchain and target
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+}
+
+impl CheckOptions {
+    /// Whether the check expands into a matrix of runs over `toolchains` and `targets`.
+    pub const fn has_matrix(&self) -> bool {
+        !self.toolchains.is_empty() || !self.targets.is_empty()
+    }
+}
+
+/// Options for the `features` check, set under `[preflight.features]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeaturesConfig {
+    /// Largest number of features to combine, 1 checks each feature on its own
+    pub depth: usize,
+    /// Features never included in a combination, or in the all features run
+    pub exclude: Vec<String>,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            depth: 1,
+            exclude: vec![],
+        }
+    }
+}
+
+/// Options for the `semver` check, set under `[preflight.semver]`.
+///
+/// Without a baseline, the most recent tag before `HEAD` is used.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SemverConfig {
+    /// Git revision to compare against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_rev: Option<String>,
+    /// Locally cached `.crate` file to compare against, takes priority over `baseline_rev`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline_crate: Option<PathBuf>,
+}
+
+/// Options for the `package` check, set under `[preflight.package]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackageConfig {
+    /// Largest packaged file allowed, in bytes
+    pub max_file_size: u64,
+    /// Packaged files allowed to exceed `max_file_size`, relative to the package root
+    pub allow_large_files: Vec<String>,
+}
+
+impl Default for PackageConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size: 5 * 1024 * 1024,
+            allow_large_files: vec![],
+        }
+    }
+}
+
+/// Options for the `doc` check, set under `[preflight.doc]`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocConfig {
+    /// Also deny missing documentation on public items
+    pub missing_docs: bool,
+}
+
+/// Options for the `test` check, set under `[preflight.test]`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestConfig {
+    /// Test runner to use, falling back to `cargo test` if nextest isn't installed
+    pub runner: TestRunner,
+    /// Nextest profile to run with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Times a failed test is rerun, reporting it as flaky if it then passes
+    pub retries: u32,
+    /// Tests whose failures are reported without failing the check, by path such as `tests::slow`
+    pub quarantine: Vec<String>,
+    /// On commit, only run tests in packages affected by staged changes, and touched test files
+    pub impact: bool,
+}
+
+/// Options for the `coverage` check, set under `[preflight.coverage]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoverageConfig {
+    /// Lowest line coverage allowed across the workspace, as a percentage
+    pub min_total: f64,
+    /// Lowest line coverage allowed for lines changed since `base_rev`, as a percentage
+    pub min_changed: f64,
+    /// Git revision changed lines are found from, falling back to the upstream branch and then `HEAD`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_rev: Option<String>,
+}
+
+impl Default for CoverageConfig {
+    fn default() -> Self {
+        Self {
+            min_total: 0.0,
+            min_changed: 80.0,
+            base_rev: None,
+        }
+    }
+}
+
+/// Options for the `licenses` check, set under `[preflight.licenses]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LicensesConfig {
+    /// SPDX license identifiers dependencies may use, any license that isn't denied if empty
+    pub allow: Vec<String>,
+    /// SPDX license identifiers dependencies may not use
+    pub deny: Vec<String>,
+    /// Crates exempt from license checks, such as those only setting `license-file`
+    pub license_exceptions: Vec<String>,
+    /// Crates that may not be depended on, as `name` or `name@<version requirement>`
+    pub banned: Vec<String>,
+    /// Most versions of a single crate allowed in the dependency graph
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_versions: Option<usize>,
+    /// Crates allowed to exceed `max_versions`
+    pub allow_duplicates: Vec<String>,
+    /// Registry index URLs dependencies may come from
+    pub allow_registries: Vec<String>,
+    /// URL prefixes of git repositories dependencies may come from
+    pub allow_git: Vec<String>,
+}
+
+impl Default for LicensesConfig {
+    fn default() -> Self {
+        Self {
+            allow: vec![],
+            deny: vec![],
+            license_exceptions: vec![],
+            banned: vec![],
+            max_versions: None,
+            allow_duplicates: vec![],
+            allow_registries: vec![
+                "https://github.com/rust-lang/crates.io-index".to_owned(),
+                "https://index.crates.io/".to_owned(),
+            ],
+            allow_git: vec![],
+        }
+    }
+}
+
+/// Options for the `audit` check, set under `[preflight.audit]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Local checkout of the `RustSec` advisory database, `~/.cargo/advisory-db` if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub db_path: Option<PathBuf>,
+    /// Advisories to ignore, optionally until a date
+    pub ignore: Vec<IgnoredAdvisory>,
+    /// Fail on yanked crates, found in the local registry index cache
+    pub deny_yanked: bool,
+    /// Fail on informational advisories, such as unmaintained or unsound crates
+    pub deny_informational: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            db_path: None,
+            ignore: vec![],
+            deny_yanked: true,
+            deny_informational: false,
+        }
+    }
+}
+
+/// Options for the `unused_deps` check, set under `[preflight.shear]`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShearConfig {
+    /// Workspace packages to check, all of them if empty
+    pub packages: Vec<String>,
+    /// Workspace packages not to check
+    pub exclude: Vec<String>,
+    /// Unused dependencies to ignore, on top of `[package.metadata.cargo-shear]`
+    pub ignored: Vec<String>,
+    /// Expand macros with `cargo expand` before looking for uses, which requires nightly
+    pub expand: bool,
+}
+
+/// Options for the `secrets` check, set under `[preflight.secrets]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecretsConfig {
+    /// What to search: the files on disk, or only what's being committed or pushed
+    pub mode: SecretsMode,
+    /// Paths to search, respecting `.gitignore` and `.secretsignore` when searching files on disk
+    pub paths: Vec<PathBuf>,
+    /// Extra regexes to search for, on top of ripsecrets' own
+    pub additional_patterns: Vec<String>,
+    /// Fingerprints of known false positives
+    pub allowlist: Vec<String>,
+    /// File of fingerprints for existing secrets, which don't fail the check
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<PathBuf>,
+    /// Also search the messages of commits being pushed
+    pub commit_messages: bool,
+}
+
+/// What the `secrets` check searches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretsMode {
+    /// Files on disk under the configured paths
+    #[default]
+    Tree,
+    /// Staged files when committing, and files changed by the pushed commits when pushing
+    Changes,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            mode: SecretsMode::default(),
+            paths: vec![PathBuf::from(".")],
+            additional_patterns: vec![],
+            allowlist: vec![],
+            baseline: None,
+            commit_messages: false,
+        }
+    }
+}
+
+/// Options for the `forbidden_patterns` check, set under `[preflight.forbidden_patterns]`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ForbiddenPatternsConfig {
+    /// Built-in rules to turn off, such as `println` or `todo`
+    pub disable: Vec<String>,
+    /// Rules of your own, checked after the built-in ones
+    pub rules: Vec<ForbiddenRule>,
+}
+
+/// A pattern the `forbidden_patterns` check looks for in added lines.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForbiddenRule {
+    pub name: String,
+    /// Regex matched against each added line
+    pub pattern: String,
+    /// Globs of files the rule applies to, relative to the repository root, all files if empty
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Globs of files the rule doesn't apply to
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Explanation shown with each match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Whether a finding fails its check, or is only reported.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
+/// Options for the `file_size` check, set under `[preflight.file_size]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSizeConfig {
+    /// Largest file allowed, in bytes
+    pub max_size: u64,
+    /// Globs of files that shouldn't be committed, such as keys and databases
+    pub forbidden: Vec<String>,
+    /// Globs of files exempt from both limits
+    pub allow: Vec<String>,
+}
+
+impl Default for FileSizeConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 1024 * 1024,
+            forbidden: [
+                "*.pem",
+                "*.key",
+                "*.p12",
+                "*.pfx",
+                "*.sqlite",
+                "*.sqlite3",
+                "*.db",
+            ]
+            .map(ToOwned::to_owned)
+            .to_vec(),
+            allow: vec![],
+        }
+    }
+}
+
+/// An advisory the `audit` check ignores.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IgnoredAdvisory {
+    /// Advisory ID, such as `RUSTSEC-2023-0071`
+    pub id: String,
+    /// Last day the advisory is ignored, after which it's reported again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<Datetime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Runner used by the `test` check.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestRunner {
+    #[default]
+    Cargo,
+    Nextest,
+}
+
+impl fmt::Display for CheckOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        if let Some(toolchain) = &self.toolchain {
+            parts.push(format!("+{toolchain}"));
+        }
+        parts.extend(self.args.iter().cloned());
+        if let Some(target) = &self.target {
+            parts.push(format!("--target {target}"));
+        }
+        if let Some(lint_args) = &self.lint_args {
+            parts.push("--".to_owned());
+            parts.extend(lint_args.iter().cloned());
+        }
+        if !self.toolchains.is_empty() {
+            parts.push(format!("(toolchains: {})", self.toolchains.join(", ")));
+        }
+        if !self.targets.is_empty() {
+            parts.push(format!("(targets: {})", self.targets.join(", ")));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// What to do with a failed check when Preflight can't prompt, such as in CI or GUI git clients.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnFailure {
+    #[default]
+    Fail,
+    Autofix,
+    Override,
+}
+
+impl fmt::Display for OnFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fail => write!(f, "fail"),
+            Self::Autofix => write!(f, "autofix"),
+            Self::Override => write!(f, "override"),
+        }
+    }
 }
 
 impl Default for PreflightConfig {
     fn default() -> Self {
         Self {
+            name: None,
             run_when: vec!["push".into()],
             branches: vec![],
             checks: vec!["fmt".into(), "test".into()],
             autofix: true,
             over_ride: false,
+            on_failure: OnFailure::default(),
+            options: BTreeMap::new(),
+            features: FeaturesConfig::default(),
+            semver: SemverConfig::default(),
+            package: PackageConfig::default(),
+            doc: DocConfig::default(),
+            test: TestConfig::default(),
+            coverage: CoverageConfig::default(),
+            licenses: LicensesConfig::default(),
+            audit: AuditConfig::default(),
+            shear: ShearConfig::default(),
+            secrets: SecretsConfig::default(),
+            forbidden_patterns: ForbiddenPatternsConfig::default(),
+            file_size: FileSizeConfig::default(),
         }
     }
 }
@@ -0,0 +1,297 @@
+use anyhow::Result;
+use cargo_metadata::MetadataCommand;
+use colored::Colorize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use crate::{
+    checks::{COVERAGE_ARGS, cargo_command},
+    config::{CheckOptions, CoverageConfig},
+    error::PreflightError,
+    git::get_changed_lines,
+};
+
+/// Build directory for instrumented builds under the workspace's target directory, kept apart so they don't
+/// invalidate regular builds.
+const COVERAGE_TARGET_DIR: &str = "preflight-coverage";
+
+/// Execution counts for each instrumented line, by absolute file path.
+type LineCounts = BTreeMap<PathBuf, BTreeMap<u32, u64>>;
+
+fn coverage_error(coverage_report: impl Into<String>) -> anyhow::Error {
+    PreflightError::CoverageFailed {
+        coverage_report: coverage_report.into(),
+    }
+    .into()
+}
+
+fn command_output(output: &Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// The `llvm-tools` binary directory of the toolchain the check runs with.
+fn llvm_tools_dir(options: &CheckOptions) -> Result<PathBuf> {
+    let rustc = |args: &[&str]| -> Result<String> {
+        let mut cmd = Command::new("rustc");
+        if let Some(toolchain) = &options.toolchain {
+            cmd.arg(format!("+{toolchain}"));
+        }
+        Ok(String::from_utf8_lossy(&cmd.args(args).output()?.stdout).into_owned())
+    };
+    let sysroot = rustc(&["--print", "sysroot"])?;
+    let version = rustc(&["-vV"])?;
+    let host = version
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .unwrap_or_default();
+
+    let dir = Path::new(sysroot.trim())
+        .join("lib/rustlib")
+        .join(host)
+        .join("bin");
+    if dir.join("llvm-profdata").exists() && dir.join("llvm-cov").exists() {
+        Ok(dir)
+    } else {
+        Err(coverage_error(
+            "`llvm-tools` is not installed, install it with `rustup component add llvm-tools`",
+        ))
+    }
+}
+
+/// `cargo test` with coverage instrumentation, writing profiles to `profile_dir`.
+pub fn coverage_command(options: &CheckOptions, profile_dir: &Path) -> Result<Command> {
+    let target_dir = MetadataCommand::new()
+        .no_deps()
+        .exec()?
+        .target_directory
+        .join(COVERAGE_TARGET_DIR);
+    let mut cmd = cargo_command(
+        &COVERAGE_ARGS,
+        options,
+        &[
+            "--target-dir".to_owned(),
+            target_dir.to_string(),
+            "--message-format=json".to_owned(),
+        ],
+    );
+    let rustflags = options
+        .env
+        .get("RUSTFLAGS")
+        .cloned()
+        .or_else(|| env::var("RUSTFLAGS").ok())
+        .map_or_else(
+            || "-C instrument-coverage".to_owned(),
+            |flags| format!("{flags} -C instrument-coverage"),
+        );
+    cmd.env("RUSTFLAGS", rustflags)
+        .env("LLVM_PROFILE_FILE", profile_dir.join("%p-%m.profraw"));
+    Ok(cmd)
+}
+
+/// Test executables built by cargo, from its JSON messages.
+fn test_executables(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| {
+            message["reason"] == "compiler-artifact" && message["profile"]["test"] == true
+        })
+        .filter_map(|message| message["executable"].as_str().map(ToOwned::to_owned))
+        .collect()
+}
+
+/// Line execution counts from an lcov report.
+fn parse_lcov(lcov: &str) -> LineCounts {
+    let mut counts = LineCounts::new();
+    let mut file = None;
+    for line in lcov.lines() {
+        if let Some(path) = line.strip_prefix("SF:") {
+            file = Some(PathBuf::from(path));
+        } else if let (Some(file), Some(record)) = (&file, line.strip_prefix("DA:")) {
+            let mut fields = record.split(',');
+            if let (Some(Ok(line)), Some(Ok(count))) = (
+                fields.next().map(str::parse::<u32>),
+                fields.next().map(str::parse::<u64>),
+            ) {
+                *counts
+                    .entry(file.clone())
+                    .or_default()
+                    .entry(line)
+                    .or_default() += count;
+            }
+        } else if line == "end_of_record" {
+            file = None;
+        }
+    }
+    counts
+}
+
+/// Covered and total instrumented lines.
+fn line_coverage<'a>(lines: impl Iterator<Item = &'a u64>) -> (usize, usize) {
+    lines.fold((0, 0), |(covered, total), count| {
+        (covered + usize::from(*count > 0), total + 1)
+    })
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn percentage((covered, total): (usize, usize)) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / total as f64
+    }
+}
+
+/// Collapse sorted line numbers into ranges, such as `3, 7-9`.
+fn line_ranges(lines: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Run the tests with coverage instrumentation and export line counts for workspace files.
+fn collect_coverage(options: &CheckOptions) -> Result<LineCounts> {
+    let tools = llvm_tools_dir(options)?;
+    let profile_dir = tempfile::tempdir()?;
+
+    let output = coverage_command(options, profile_dir.path())?.output()?;
+    if !output.status.success() {
+        // Test output is mixed in with cargo's JSON messages
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let test_output = stdout
+            .lines()
+            .filter(|line| serde_json::from_str::<Value>(line).is_err())
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Err(coverage_error(format!(
+            "Tests failed while collecting coverage:\n{test_output}\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    let executables = test_executables(&String::from_utf8_lossy(&output.stdout));
+    let Some((first, others)) = executables.split_first() else {
+        return Err(coverage_error("No test executables were built"));
+    };
+
+    let profiles: Vec<PathBuf> = std::fs::read_dir(profile_dir.path())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "profraw"))
+        .collect();
+    let merged = profile_dir.path().join("merged.profdata");
+    let output = Command::new(tools.join("llvm-profdata"))
+        .args(["merge", "-sparse", "-o"])
+        .arg(&merged)
+        .args(&profiles)
+        .output()?;
+    if !output.status.success() {
+        return Err(coverage_error(command_output(&output)));
+    }
+
+    let mut export = Command::new(tools.join("llvm-cov"));
+    export
+        .args(["export", "-format=lcov", "-instr-profile"])
+        .arg(&merged)
+        .args(["-ignore-filename-regex", r"/\.cargo/|/rustc/|/target/"])
+        .arg(first);
+    for executable in others {
+        export.args(["-object", executable]);
+    }
+    let output = export.output()?;
+    if !output.status.success() {
+        return Err(coverage_error(command_output(&output)));
+    }
+
+    let root = env::current_dir()?;
+    let mut counts = parse_lcov(&String::from_utf8_lossy(&output.stdout));
+    counts.retain(|path, _| path.starts_with(&root));
+    Ok(counts)
+}
+
+pub fn coverage(cfg: &CoverageConfig, options: &CheckOptions) -> Result<()> {
+    let counts = collect_coverage(options)?;
+    let root = env::current_dir()?;
+    let changed = get_changed_lines(cfg.base_rev.as_deref())?;
+
+    let total = percentage(line_coverage(counts.values().flat_map(BTreeMap::values)));
+
+    let mut changed_counts = Vec::new();
+    let mut uncovered = Vec::new();
+    for (path, lines) in &counts {
+        let Some(changed_lines) = changed.get(path) else {
+            continue;
+        };
+        let instrumented: BTreeMap<&u32, &u64> = lines
+            .iter()
+            .filter(|(line, _)| changed_lines.contains(line))
+            .collect();
+        let missed: Vec<u32> = instrumented
+            .iter()
+            .filter(|(_, count)| ***count == 0)
+            .map(|(line, _)| **line)
+            .collect();
+        if !missed.is_empty() {
+            uncovered.push(format!(
+                "        - {}: {}",
+                path.strip_prefix(&root).unwrap_or(path).display(),
+                line_ranges(&missed)
+            ));
+        }
+        changed_counts.extend(instrumented.into_values().copied());
+    }
+    let changed_total = line_coverage(changed_counts.iter());
+    let changed_percentage = percentage(changed_total);
+
+    let summary = if changed_total.1 == 0 {
+        format!("total {total:.1}%, no changed lines")
+    } else {
+        format!("total {total:.1}%, changed lines {changed_percentage:.1}%")
+    };
+    let mut failures = Vec::new();
+    if total < cfg.min_total {
+        failures.push(format!(
+            "    Total line coverage {total:.1}% is below {:.1}%",
+            cfg.min_total
+        ));
+    }
+    if changed_total.1 > 0 && changed_percentage < cfg.min_changed {
+        failures.push(format!(
+            "    Changed line coverage {changed_percentage:.1}% is below {:.1}%, uncovered changed lines:\n{}",
+            cfg.min_changed,
+            uncovered.join("\n")
+        ));
+    }
+
+    if failures.is_empty() {
+        println!(
+            "    {}",
+            format!("[√] Coverage preflight check passed ({summary})").green()
+        );
+        Ok(())
+    } else {
+        Err(coverage_error(failures.join("\n")))
+    }
+}
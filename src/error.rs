@@ -35,6 +35,73 @@ pub enum PreflightError {
     #[error("    {}{test_outputs}", "[x] Test preflight check failed:\n".red().bold())]
     TestsFailed { test_outputs: String },
 
+    /// Line coverage from running tests with LLVM coverage was below a threshold
+    #[error("    {}{coverage_report}", "[x] Coverage preflight check failed:\n".red().bold())]
+    CoverageFailed { coverage_report: String },
+
+    /// `cargo doc --no-deps` preflight check failed
+    #[error("    {}{doc_output}", "[x] Docs preflight check failed:\n".red().bold())]
+    DocFailed { doc_output: String },
+
+    /// `cargo test --doc` preflight check failed
+    #[error("    {}{doctest_outputs}", "[x] Doctests preflight check failed:\n".red().bold())]
+    DoctestFailed { doctest_outputs: String },
+
+    /// `cargo check` failed with the toolchain matching `rust-version`, or no such toolchain is installed
+    #[error("    {}{msrv_output}", "[x] MSRV preflight check failed:\n".red().bold())]
+    MsrvFailed { msrv_output: String },
+
+    /// A check failed for at least one toolchain and target in its matrix
+    #[error("    {}{matrix_output}", format!("[x] {check} matrix preflight check failed:\n").red().bold())]
+    MatrixFailed {
+        check: String,
+        matrix_output: String,
+    },
+
+    /// `cargo check` failed for at least one feature combination
+    #[error("    {}{failed_combinations}", "[x] Feature combinations preflight check failed:\n".red().bold())]
+    FeaturesFailed { failed_combinations: String },
+
+    /// `cargo semver-checks` found breaking changes not matching the version bump, or couldn't run
+    #[error("    {}{semver_report}", "[x] Semver preflight check failed:\n".red().bold())]
+    SemverFailed { semver_report: String },
+
+    /// `cargo publish --dry-run` preflight check failed
+    #[error("    {}{publish_output}", "[x] Publish dry run preflight check failed:\n".red().bold())]
+    PublishDryRunFailed { publish_output: String },
+
+    /// The files packaged by `cargo package` include something they shouldn't, or miss something they should
+    #[error("    {}{package_issues}", "[x] Package preflight check failed:\n".red().bold())]
+    PackageFailed { package_issues: String },
+
+    /// The git tree has uncommitted changes
+    #[error("    {}{dirty_files}", "[x] Clean tree preflight check failed, uncommitted changes in:\n".red().bold())]
+    CleanTreeFailed { dirty_files: String },
+
+    /// No git tag exists for the version of a package being published
+    #[error("    {}{missing_tags}", "[x] Version tag preflight check failed, no tag for:\n".red().bold())]
+    VersionTagFailed { missing_tags: String },
+
+    /// Dependencies broke the license, ban, duplicate or source policy
+    #[error("    {}{license_issues}", "[x] Licenses preflight check failed:\n".red().bold())]
+    LicensesFailed { license_issues: String },
+
+    /// Dependencies in `Cargo.lock` are affected by advisories, or yanked
+    #[error("    {}{audit_report}", "[x] Audit preflight check failed:\n".red().bold())]
+    AuditFailed { audit_report: String },
+
+    /// `Cargo.lock` isn't committed, or doesn't match the manifests
+    #[error("    {}{lockfile_issues}", "[x] Lockfile preflight check failed:\n".red().bold())]
+    LockfileFailed { lockfile_issues: String },
+
+    /// Staged changes add lines matching forbidden patterns
+    #[error("    {}{forbidden_patterns}", "[x] Forbidden patterns preflight check failed:\n".red().bold())]
+    ForbiddenPatternsFailed { forbidden_patterns: String },
+
+    /// Staged or pushed files are too large, or match forbidden patterns
+    #[error("    {}{file_size_issues}", "[x] File size preflight check failed:\n".red().bold())]
+    FileSizeFailed { file_size_issues: String },
+
     /// `cargo shear` preflight check failed
     #[error("    {}{shear_output}", "[x] Unused dependencies preflight check failed:\n".red().bold())]
     ShearFailed { shear_output: String },
@@ -45,6 +112,14 @@ pub enum PreflightError {
 
     #[error("    {}{failed_check}", "Preflight ended due to failed check: ".red().bold())]
     OverrideCancelled { failed_check: String },
+
+    /// A check failed and neither override nor autofix was available or chosen
+    #[error("    {}{failed_check}", "Preflight failed, no override or autofix for check: ".red().bold())]
+    CheckFailed { failed_check: String },
+
+    /// A prompt could not be shown, for example because there is no terminal attached
+    #[error("{}{prompt_error}", "Unable to prompt, failing preflight: ".red())]
+    PromptFailed { prompt_error: String },
 }
 
 impl From<PreflightError> for std::io::Error {
@@ -62,11 +137,29 @@ pub fn failed_check_index(checks: &[String], error: &PreflightError) -> Option<u
         PreflightError::CheckExamplesFailed { .. } => "check_examples",
         PreflightError::CheckBenchesFailed { .. } => "check_benches",
         PreflightError::TestsFailed { .. } => "test",
+        PreflightError::CoverageFailed { .. } => "coverage",
+        PreflightError::DocFailed { .. } => "doc",
+        PreflightError::DoctestFailed { .. } => "doctest",
+        PreflightError::MsrvFailed { .. } => "msrv",
+        PreflightError::MatrixFailed { check, .. } => check.as_str(),
+        PreflightError::FeaturesFailed { .. } => "features",
+        PreflightError::SemverFailed { .. } => "semver",
+        PreflightError::PublishDryRunFailed { .. } => "publish_dry_run",
+        PreflightError::PackageFailed { .. } => "package",
+        PreflightError::CleanTreeFailed { .. } => "clean_tree",
+        PreflightError::VersionTagFailed { .. } => "version_tag",
+        PreflightError::LicensesFailed { .. } => "licenses",
+        PreflightError::AuditFailed { .. } => "audit",
+        PreflightError::LockfileFailed { .. } => "lockfile",
+        PreflightError::ForbiddenPatternsFailed { .. } => "forbidden_patterns",
+        PreflightError::FileSizeFailed { .. } => "file_size",
         PreflightError::ShearFailed { .. } => "unused_deps",
         PreflightError::SecretsFailed { .. } => "secrets",
         PreflightError::InvalidCheck { config } => config.as_str(),
         PreflightError::InvalidHook { .. } => "hook",
-        PreflightError::OverrideCancelled { .. } => unreachable!(),
+        PreflightError::OverrideCancelled { .. }
+        | PreflightError::CheckFailed { .. }
+        | PreflightError::PromptFailed { .. } => unreachable!(),
     };
 
     // Find the index of the failed check in the checks vector
@@ -0,0 +1,163 @@
+use anyhow::Result;
+use cargo_metadata::MetadataCommand;
+use colored::Colorize;
+use std::process::Command;
+
+use crate::{
+    checks::{FEATURES_ARGS, cargo_command},
+    config::{CheckOptions, FeaturesConfig},
+    error::PreflightError,
+};
+
+/// All combinations of `features` with between 1 and `depth` features, in order of size.
+fn combinations(features: &[String], depth: usize) -> Vec<Vec<String>> {
+    fn extend(
+        features: &[String],
+        size: usize,
+        current: &mut Vec<String>,
+        out: &mut Vec<Vec<String>>,
+    ) {
+        if current.len() == size {
+            out.push(current.clone());
+            return;
+        }
+        for (index, feature) in features.iter().enumerate() {
+            current.push(feature.clone());
+            extend(&features[index + 1..], size, current, out);
+            current.pop();
+        }
+    }
+
+    let mut out = Vec::new();
+    for size in 1..=depth.min(features.len()) {
+        extend(features, size, &mut Vec::new(), &mut out);
+    }
+    out
+}
+
+/// Arguments enabling every feature, leaving out excluded ones (and the default set, which may enable them).
+fn all_features_args(features: &[String], exclude: &[String]) -> Vec<String> {
+    if exclude.is_empty() {
+        vec!["--all-features".to_owned()]
+    } else {
+        vec![
+            "--no-default-features".to_owned(),
+            "--features".to_owned(),
+            features.join(","),
+        ]
+    }
+}
+
+/// The `cargo check` commands for every feature combination of every workspace package, labelled by their feature arguments.
+pub fn feature_commands(
+    cfg: &FeaturesConfig,
+    options: &CheckOptions,
+) -> Result<Vec<(String, Command)>> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+
+    let mut commands = Vec::new();
+    for package in metadata.workspace_packages() {
+        let features: Vec<String> = package
+            .features
+            .keys()
+            .filter(|feature| *feature != "default" && !cfg.exclude.contains(feature))
+            .cloned()
+            .collect();
+
+        let mut feature_args = vec![
+            vec!["--no-default-features".to_owned()],
+            all_features_args(&features, &cfg.exclude),
+        ];
+        feature_args.extend(
+            combinations(&features, cfg.depth)
+                .into_iter()
+                .map(|combination| {
+                    vec![
+                        "--no-default-features".to_owned(),
+                        "--features".to_owned(),
+                        combination.join(","),
+                    ]
+                }),
+        );
+
+        for args in feature_args {
+            let label = format!("{} {}", package.name, args.join(" "));
+            let mut extra_args = vec!["-p".to_owned(), package.name.to_string()];
+            extra_args.extend(args);
+            commands.push((label, cargo_command(&FEATURES_ARGS, options, &extra_args)));
+        }
+    }
+    Ok(commands)
+}
+
+pub fn features(cfg: &FeaturesConfig, options: &CheckOptions) -> Result<()> {
+    let mut failed = Vec::new();
+    let commands = feature_commands(cfg, options)?;
+    let total = commands.len();
+    for (label, mut cmd) in commands {
+        let output = cmd.output()?;
+        if !output.status.success() {
+            let errors = String::from_utf8_lossy(&output.stderr)
+                .lines()
+                .filter(|line| line.starts_with("error"))
+                .map(|line| format!("        {line}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            failed.push(format!("    {}\n{errors}", label.bold()));
+        }
+    }
+
+    if failed.is_empty() {
+        println!(
+            "    {}",
+            format!("[√] Feature combinations preflight check passed ({total} combinations)")
+                .green()
+        );
+        Ok(())
+    } else {
+        Err(PreflightError::FeaturesFailed {
+            failed_combinations: format!(
+                "{} of {total} combinations failed:\n{}",
+                failed.len(),
+                failed.join("\n")
+            ),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn combines_up_to_depth() {
+        assert_eq!(
+            combinations(&features(&["a", "b", "c"]), 2),
+            [
+                features(&["a"]),
+                features(&["b"]),
+                features(&["c"]),
+                features(&["a", "b"]),
+                features(&["a", "c"]),
+                features(&["b", "c"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn excluded_features_are_left_out_of_all_features() {
+        assert_eq!(
+            all_features_args(&features(&["a", "b"]), &[]),
+            ["--all-features"]
+        );
+        assert_eq!(
+            all_features_args(&features(&["a", "b"]), &features(&["nightly"])),
+            ["--no-default-features", "--features", "a,b"]
+        );
+    }
+}
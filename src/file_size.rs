@@ -0,0 +1,67 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::{
+    config::FileSizeConfig,
+    error::PreflightError,
+    git::{GitBlob, get_pushed_blobs, get_staged_blobs},
+    util::glob_set,
+};
+
+/// Where a blob comes from, with the commit that added it when pushing.
+fn blob_label(blob: &GitBlob) -> String {
+    blob.commit.map_or_else(
+        || blob.path.display().to_string(),
+        |commit| format!("{} in {}", blob.path.display(), &commit.to_string()[..7]),
+    )
+}
+
+pub fn file_size(cfg: &FileSizeConfig, hook: &str) -> Result<()> {
+    let blobs = if hook == "push" {
+        get_pushed_blobs()?
+    } else {
+        get_staged_blobs()?
+    };
+    let allowed = glob_set(&cfg.allow)?;
+    let forbidden = glob_set(&cfg.forbidden)?;
+
+    let mut issues = Vec::new();
+    for blob in blobs.iter().filter(|blob| !allowed.is_match(&blob.path)) {
+        if let Some(&index) = forbidden.matches(&blob.path).first() {
+            issues.push(format!(
+                "{} matches forbidden pattern `{}`",
+                blob_label(blob),
+                cfg.forbidden[index]
+            ));
+        }
+        if blob.size > cfg.max_size {
+            issues.push(format!(
+                "{} is {} bytes, larger than the {} byte limit",
+                blob_label(blob),
+                blob.size,
+                cfg.max_size
+            ));
+        }
+    }
+
+    if issues.is_empty() {
+        println!(
+            "    {}",
+            format!(
+                "[√] File size preflight check passed ({} files)",
+                blobs.len()
+            )
+            .green()
+        );
+        Ok(())
+    } else {
+        Err(PreflightError::FileSizeFailed {
+            file_size_issues: issues
+                .iter()
+                .map(|issue| format!("        - {issue}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+        .into())
+    }
+}
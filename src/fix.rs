@@ -3,32 +3,65 @@ use colored::Colorize;
 use inquire::Confirm;
 use std::process::Command;
 
-use crate::{config::PreflightConfig, error::PreflightError, preflight::preflight_checks};
+use crate::{
+    checks::run_checks,
+    config::{CheckOptions, PreflightConfig},
+    error::PreflightError,
+    preflight::{RunOptions, preflight_checks},
+    shear::fix_shear,
+};
 
-pub fn over_ride(cfg: &PreflightConfig, index: usize) -> Result<()> {
+pub fn over_ride(cfg: &PreflightConfig, opts: &RunOptions, index: usize) -> Result<()> {
     let check = &cfg.checks[index];
-    let ans = Confirm::new(&format!("Do you want to override {check} preflight check?",))
+    let ans = Confirm::new(&format!("Do you want to override {check} preflight check?"))
         .with_default(false)
         .with_help_message(&format!(
             "This will skip {check} and continue preflight checks",
         ))
-        .prompt()?;
+        .prompt()
+        .map_err(|err| PreflightError::PromptFailed {
+            prompt_error: err.to_string(),
+        })?;
 
     if ans {
-        println!("Skipping {check}...");
-        preflight_checks(cfg, index + 1)?;
+        skip_check(cfg, opts, index)
     } else {
-        return Err(PreflightError::OverrideCancelled {
+        Err(PreflightError::OverrideCancelled {
             failed_check: check.to_owned(),
         }
-        .into());
+        .into())
     }
+}
 
-    Ok(())
+/// Skip the failed check at `index` and continue with the remaining checks.
+pub fn skip_check(cfg: &PreflightConfig, opts: &RunOptions, index: usize) -> Result<()> {
+    println!("Skipping {}...", cfg.checks[index]);
+    preflight_checks(cfg, opts, index + 1)
+}
+
+/// Autofix the failed check at `index`, then rerun it and the remaining checks.
+///
+/// The fixed check is rerun on its own first, so a failure autofix can't clear fails the run instead of being
+/// autofixed again.
+pub fn apply_autofix(cfg: &PreflightConfig, opts: &RunOptions, index: usize) -> Result<()> {
+    let check = &cfg.checks[index];
+    autofix(check, cfg)?;
+    if let Err(e) = run_checks(std::slice::from_ref(check), cfg, &opts.hook) {
+        println!("{e:?}");
+        return Err(PreflightError::CheckFailed {
+            failed_check: check.to_owned(),
+        }
+        .into());
+    }
+    preflight_checks(cfg, opts, index + 1)
 }
 
-pub fn fix_cargo_fmt() -> Result<()> {
-    let output = Command::new("cargo").arg("fmt").output()?;
+pub fn fix_cargo_fmt(options: &CheckOptions) -> Result<()> {
+    let output = Command::new("cargo")
+        .arg("fmt")
+        .args(&options.args)
+        .envs(&options.env)
+        .output()?;
 
     if output.status.success() {
         println!("    {}", "[√] Applying fmt successful".yellow());
@@ -42,11 +75,16 @@ pub fn fix_cargo_fmt() -> Result<()> {
     }
 }
 
-pub fn fix_cargo_clippy() -> Result<()> {
-    let output = Command::new("cargo")
-        .env("__CARGO_FIX_YOLO", "1")
+pub fn fix_cargo_clippy(options: &CheckOptions) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    cmd.env("__CARGO_FIX_YOLO", "1")
         .args(["clippy", "--fix", "--allow-dirty"])
-        .output()?;
+        .args(&options.args)
+        .envs(&options.env);
+    if let Some(target) = &options.target {
+        cmd.args(["--target", target]);
+    }
+    let output = cmd.output()?;
 
     if output.status.success() {
         println!(
@@ -62,10 +100,12 @@ pub fn fix_cargo_clippy() -> Result<()> {
     }
 }
 
-pub fn autofix(check: &str) -> Result<()> {
+pub fn autofix(check: &str, cfg: &PreflightConfig) -> Result<()> {
+    let options = &cfg.check_options(check);
     match check {
-        "fmt" => fix_cargo_fmt(),
-        "clippy" => fix_cargo_clippy(),
+        "fmt" => fix_cargo_fmt(options),
+        "clippy" => fix_cargo_clippy(options),
+        "unused_deps" => fix_shear(&cfg.shear),
         _ => Err(PreflightError::InvalidCheck {
             config: check.to_owned(),
         }
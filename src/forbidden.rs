@@ -0,0 +1,430 @@
+use anyhow::Result;
+use cargo_metadata::{MetadataCommand, Target, camino::Utf8Path};
+use colored::Colorize;
+use globset::{GlobSet, escape};
+use regex::Regex;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{ForbiddenPatternsConfig, ForbiddenRule, Severity},
+    error::PreflightError,
+    git::{get_repo_root, get_staged_lines},
+    util::glob_set,
+};
+
+/// A rule that's on unless disabled in config.
+struct BuiltinRule {
+    name: &'static str,
+    pattern: &'static str,
+    paths: &'static [&'static str],
+    exclude: &'static [&'static str],
+    /// Only applies to library targets' source files, outside binary targets, instead of `paths`
+    library: bool,
+    message: &'static str,
+}
+
+const CONFLICT_MARKER: &str = "conflict_marker";
+
+const BUILTIN_RULES: [BuiltinRule; 6] = [
+    BuiltinRule {
+        name: "dbg",
+        pattern: r"\bdbg!\s*\(",
+        paths: &["**/*.rs"],
+        exclude: &[],
+        library: false,
+        message: "`dbg!` left in",
+    },
+    BuiltinRule {
+        name: "todo",
+        pattern: r"\btodo!\s*\(",
+        paths: &["**/*.rs"],
+        exclude: &[],
+        library: false,
+        message: "`todo!` left in",
+    },
+    BuiltinRule {
+        name: "unimplemented",
+        pattern: r"\bunimplemented!\s*\(",
+        paths: &["**/*.rs"],
+        exclude: &[],
+        library: false,
+        message: "`unimplemented!` left in",
+    },
+    BuiltinRule {
+        name: "println",
+        pattern: r"\bprintln!\s*\(",
+        paths: &[],
+        exclude: &[],
+        // Binaries print on purpose
+        library: true,
+        message: "`println!` in library code",
+    },
+    BuiltinRule {
+        name: "ignore_without_reason",
+        pattern: r"#\[ignore\]",
+        paths: &["**/*.rs"],
+        exclude: &[],
+        library: false,
+        message: "`#[ignore]` without a reason, use `#[ignore = \"reason\"]`",
+    },
+    BuiltinRule {
+        name: CONFLICT_MARKER,
+        // `=======` is only a marker between `<<<<<<<` and `>>>>>>>`, as it's also a heading underline
+        pattern: r"^(<{7}|={7}|>{7})( |$)",
+        paths: &["**"],
+        exclude: &[],
+        library: false,
+        message: "merge conflict marker",
+    },
+];
+
+/// A rule compiled for matching.
+struct Rule {
+    name: String,
+    pattern: Regex,
+    paths: GlobSet,
+    exclude: GlobSet,
+    severity: Severity,
+    message: String,
+}
+
+impl Rule {
+    fn new(rule: &ForbiddenRule) -> Result<Self> {
+        let paths = if rule.paths.is_empty() {
+            glob_set(&["**"])?
+        } else {
+            glob_set(&rule.paths)?
+        };
+        Ok(Self {
+            name: rule.name.clone(),
+            pattern: Regex::new(&rule.pattern)?,
+            paths,
+            exclude: glob_set(&rule.exclude)?,
+            severity: rule.severity,
+            message: rule
+                .message
+                .clone()
+                .unwrap_or_else(|| format!("matches forbidden pattern `{}`", rule.pattern)),
+        })
+    }
+
+    fn applies_to(&self, path: &Path) -> bool {
+        self.paths.is_match(path) && !self.exclude.is_match(path)
+    }
+}
+
+/// Whether a target is a library of any crate type.
+fn is_library(target: &Target) -> bool {
+    target.is_lib()
+        || target.is_rlib()
+        || target.is_dylib()
+        || target.is_cdylib()
+        || target.is_staticlib()
+        || target.is_proc_macro()
+}
+
+/// `glob` under `dir`, a path relative to the repository root.
+fn glob_under(dir: &Path, glob: &str) -> String {
+    if dir.as_os_str().is_empty() {
+        glob.to_owned()
+    } else {
+        format!("{}/{glob}", escape(&dir.display().to_string()))
+    }
+}
+
+/// Globs of library targets' source files, and of the binary targets' source files among them, for each workspace
+/// package with a library target.
+fn library_globs() -> Result<(Vec<String>, Vec<String>)> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let root = get_repo_root()?;
+    let relative = |path: &Utf8Path| {
+        path.as_std_path()
+            .strip_prefix(&root)
+            .unwrap_or_else(|_| path.as_std_path())
+            .to_owned()
+    };
+
+    let mut paths = Vec::new();
+    let mut exclude = Vec::new();
+    for package in metadata.workspace_packages() {
+        let Some(library_dir) = package
+            .targets
+            .iter()
+            .find(|target| is_library(target))
+            .and_then(|target| target.src_path.parent())
+        else {
+            continue;
+        };
+        paths.push(glob_under(&relative(library_dir), "**/*.rs"));
+        for bin in package.targets.iter().filter(|target| target.is_bin()) {
+            match bin.src_path.parent() {
+                // Such as `src/bin/<name>/main.rs`
+                Some(dir) if dir != library_dir && dir.starts_with(library_dir) => {
+                    exclude.push(glob_under(&relative(dir), "**"));
+                }
+                _ => exclude.push(escape(&relative(&bin.src_path).display().to_string())),
+            }
+        }
+    }
+    Ok((paths, exclude))
+}
+
+/// Built-in rules that aren't disabled, followed by those from config.
+fn rules(cfg: &ForbiddenPatternsConfig) -> Result<Vec<Rule>> {
+    if let Some(name) = cfg
+        .disable
+        .iter()
+        .find(|name| !BUILTIN_RULES.iter().any(|rule| rule.name == *name))
+    {
+        return Err(PreflightError::ForbiddenPatternsFailed {
+            forbidden_patterns: format!(
+                "Unknown built-in rule `{name}` in `disable` under `[preflight.forbidden_patterns]`, expected one of: {}",
+                BUILTIN_RULES.map(|rule| rule.name).join(", ")
+            ),
+        }
+        .into());
+    }
+
+    let mut rules = Vec::new();
+    for rule in BUILTIN_RULES
+        .iter()
+        .filter(|rule| !cfg.disable.iter().any(|name| name == rule.name))
+    {
+        let (paths, exclude) = if rule.library {
+            let (paths, exclude) = library_globs()?;
+            // Without a library there's nothing for the rule to apply to
+            if paths.is_empty() {
+                continue;
+            }
+            (paths, exclude)
+        } else {
+            (
+                rule.paths.iter().map(|&path| path.to_owned()).collect(),
+                rule.exclude.iter().map(|&path| path.to_owned()).collect(),
+            )
+        };
+        rules.push(Rule::new(&ForbiddenRule {
+            name: rule.name.to_owned(),
+            pattern: rule.pattern.to_owned(),
+            paths,
+            exclude,
+            severity: Severity::Error,
+            message: Some(rule.message.to_owned()),
+        })?);
+    }
+    for rule in &cfg.rules {
+        rules.push(Rule::new(rule)?);
+    }
+    Ok(rules)
+}
+
+/// Staged `=======` lines that separate the sides of a conflict, between `<<<<<<<` and `>>>>>>>` in the same file.
+fn conflict_separators(lines: &[(PathBuf, u32, String)]) -> BTreeSet<(&Path, u32)> {
+    let start = |line: &str| line == "<<<<<<<" || line.starts_with("<<<<<<< ");
+    let end = |line: &str| line == ">>>>>>>" || line.starts_with(">>>>>>> ");
+
+    let mut separators = BTreeSet::new();
+    let mut file = None;
+    let mut pending: Option<Vec<u32>> = None;
+    for (path, line_number, line) in lines {
+        if file != Some(path) {
+            file = Some(path);
+            pending = None;
+        }
+        let line = line.trim_end();
+        if start(line) {
+            pending = Some(Vec::new());
+        } else if end(line) {
+            separators.extend(
+                pending
+                    .take()
+                    .into_iter()
+                    .flatten()
+                    .map(|line_number| (path.as_path(), line_number)),
+            );
+        } else if let Some(pending) = pending.as_mut().filter(|_| line == "=======") {
+            pending.push(*line_number);
+        }
+    }
+    separators
+}
+
+pub fn forbidden_patterns(cfg: &ForbiddenPatternsConfig) -> Result<()> {
+    let rules = rules(cfg)?;
+    let lines = get_staged_lines()?;
+    let separators = conflict_separators(&lines);
+
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    for (path, line_number, line) in &lines {
+        let separator = line.starts_with("=======");
+        for rule in rules.iter().filter(|rule| {
+            rule.applies_to(path)
+                && rule.pattern.is_match(line)
+                && (rule.name != CONFLICT_MARKER
+                    || !separator
+                    || separators.contains(&(path.as_path(), *line_number)))
+        }) {
+            let finding = format!(
+                "        - {}:{line_number} [{}] {}\n            {}",
+                path.display(),
+                rule.name,
+                rule.message,
+                line.trim()
+            );
+            match rule.severity {
+                Severity::Error => errors.push(finding),
+                Severity::Warning => warnings.push(finding),
+            }
+        }
+    }
+
+    let mut report = Vec::new();
+    if !errors.is_empty() {
+        report.push(errors.join("\n"));
+    }
+    if !warnings.is_empty() {
+        report.push(format!(
+            "    {}\n{}",
+            "Warnings:".yellow(),
+            warnings.join("\n")
+        ));
+    }
+    if errors.is_empty() {
+        println!(
+            "    {}",
+            format!(
+                "[√] Forbidden patterns preflight check passed ({} staged lines)",
+                lines.len()
+            )
+            .green()
+        );
+        if !report.is_empty() {
+            println!("{}", report.join("\n"));
+        }
+        Ok(())
+    } else {
+        Err(PreflightError::ForbiddenPatternsFailed {
+            forbidden_patterns: report.join("\n"),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(name: &str) -> Regex {
+        let rule = BUILTIN_RULES
+            .iter()
+            .find(|rule| rule.name == name)
+            .expect("built-in rule exists");
+        Regex::new(rule.pattern).expect("built-in pattern is a valid regex")
+    }
+
+    fn custom_rule(paths: &[&str], exclude: &[&str]) -> Rule {
+        Rule::new(&ForbiddenRule {
+            name: "fixme".to_owned(),
+            pattern: "FIXME".to_owned(),
+            paths: paths.iter().map(ToString::to_string).collect(),
+            exclude: exclude.iter().map(ToString::to_string).collect(),
+            severity: Severity::Warning,
+            message: None,
+        })
+        .expect("rule is valid")
+    }
+
+    #[test]
+    fn builtin_patterns() {
+        let cases = [
+            ("dbg", "    let x = dbg!(y);", true),
+            ("dbg", "    log::debug!(\"x\");", false),
+            ("dbg", "    my_dbg!(y);", false),
+            ("todo", "    todo!()", true),
+            ("todo", "    // todo: handle errors", false),
+            ("unimplemented", "    unimplemented!(\"later\")", true),
+            ("println", "    println!(\"{x}\");", true),
+            ("println", "    eprintln!(\"{x}\");", false),
+            ("ignore_without_reason", "#[ignore]", true),
+            ("ignore_without_reason", "#[ignore = \"slow\"]", false),
+            ("conflict_marker", "<<<<<<< HEAD", true),
+            ("conflict_marker", "=======", true),
+            ("conflict_marker", ">>>>>>> feature", true),
+            ("conflict_marker", "<<<<<<<< not a marker", false),
+            ("conflict_marker", "    <<<<<<< indented", false),
+        ];
+        for (name, line, expected) in cases {
+            assert_eq!(pattern(name).is_match(line), expected, "{name}: {line}");
+        }
+    }
+
+    #[test]
+    fn rules_apply_to_matching_paths() {
+        let rule = custom_rule(&["src/**"], &["src/generated/**"]);
+        assert!(rule.applies_to(Path::new("src/lib.rs")));
+        assert!(rule.applies_to(Path::new("src/a/b.rs")));
+        assert!(!rule.applies_to(Path::new("src/generated/api.rs")));
+        assert!(!rule.applies_to(Path::new("tests/it.rs")));
+
+        let everywhere = custom_rule(&[], &["*.md"]);
+        assert!(everywhere.applies_to(Path::new("a/b/c.toml")));
+        assert!(!everywhere.applies_to(Path::new("README.md")));
+    }
+
+    #[test]
+    fn library_globs_are_relative_and_escaped() {
+        assert_eq!(glob_under(Path::new(""), "**/*.rs"), "**/*.rs");
+        assert_eq!(
+            glob_under(Path::new("crates/a/src"), "**"),
+            "crates/a/src/**"
+        );
+        let glob = glob_under(Path::new("crates/[a]/src"), "**/*.rs");
+        let set = glob_set(&[glob]).expect("escaped glob is valid");
+        assert!(set.is_match("crates/[a]/src/lib.rs"));
+        assert!(!set.is_match("crates/a/src/lib.rs"));
+    }
+
+    #[test]
+    fn unknown_disabled_rules_are_rejected() {
+        let cfg = ForbiddenPatternsConfig {
+            disable: vec!["printn".to_owned()],
+            rules: vec![],
+        };
+        let err = rules(&cfg).err().expect("unknown rule is an error");
+        assert!(err.to_string().contains("Unknown built-in rule `printn`"));
+
+        let cfg = ForbiddenPatternsConfig {
+            disable: BUILTIN_RULES.map(|rule| rule.name.to_owned()).to_vec(),
+            rules: vec![],
+        };
+        assert!(rules(&cfg).expect("built-in rules exist").is_empty());
+    }
+
+    #[test]
+    fn separators_need_surrounding_conflict_markers() {
+        let lines: Vec<(PathBuf, u32, String)> = [
+            ("README.md", 1, "Title"),
+            ("README.md", 2, "======="),
+            ("src/lib.rs", 10, "<<<<<<< HEAD"),
+            ("src/lib.rs", 11, "let a = 1;"),
+            ("src/lib.rs", 12, "======="),
+            ("src/lib.rs", 13, "let a = 2;"),
+            ("src/lib.rs", 14, ">>>>>>> feature"),
+            ("src/main.rs", 1, "<<<<<<< HEAD"),
+            ("src/main.rs", 2, "======="),
+            ("docs.md", 1, "======="),
+            ("docs.md", 2, ">>>>>>> feature"),
+        ]
+        .into_iter()
+        .map(|(path, line_number, line)| (PathBuf::from(path), line_number, line.to_owned()))
+        .collect();
+        assert_eq!(
+            conflict_separators(&lines),
+            BTreeSet::from([(Path::new("src/lib.rs"), 12)])
+        );
+    }
+}
@@ -1,5 +1,16 @@
 use anyhow::Result;
-use git2::{BranchType, Repository};
+use git2::{
+    BranchType, Delta, DescribeFormatOptions, DescribeOptions, Diff, FileMode, ObjectType, Oid,
+    Repository, Status, StatusOptions,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    ffi::OsStr,
+    io::stdin,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 pub fn init_symlink() -> Result<()> {
     let mut path = dirs::home_dir().expect("No valid home dir found");
@@ -35,3 +46,275 @@ pub fn get_branches() -> Result<Vec<String>, git2::Error> {
 
     Ok(branches)
 }
+
+/// The most recent tag reachable from `HEAD`'s parent, so a tag on `HEAD` itself isn't compared against.
+pub fn latest_tag() -> Option<String> {
+    let repo = Repository::open(".").ok()?;
+    let parent = repo.revparse_single("HEAD^").ok()?;
+    let describe = parent
+        .describe(DescribeOptions::new().describe_tags())
+        .ok()?;
+    describe
+        .format(Some(DescribeFormatOptions::new().abbreviated_size(0)))
+        .ok()
+}
+
+pub fn tag_exists(tag: &str) -> bool {
+    Repository::open(".").is_ok_and(|repo| repo.find_reference(&format!("refs/tags/{tag}")).is_ok())
+}
+
+/// Paths with uncommitted changes, including untracked files that aren't ignored.
+pub fn get_dirty_files() -> Result<Vec<String>> {
+    let repo = Repository::open(".")?;
+    let statuses = repo.statuses(Some(
+        StatusOptions::new()
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false),
+    ))?;
+
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| entry.path().map(String::from))
+        .collect())
+}
+
+/// The repository's working directory.
+pub fn get_repo_root() -> Result<PathBuf> {
+    let repo = Repository::open(".")?;
+    Ok(repo.workdir().map(PathBuf::from).unwrap_or_default())
+}
+
+/// Absolute paths of files whose staged changes differ from `HEAD`.
+pub fn get_staged_files() -> Result<Vec<PathBuf>> {
+    let repo = Repository::open(".")?;
+    let root = repo.workdir().map(PathBuf::from).unwrap_or_default();
+    // Before the first commit everything in the index is staged
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None)?;
+
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| root.join(path))
+        .collect())
+}
+
+/// Lines added or modified by staged changes, as a path relative to the repository root, line number and content.
+pub fn get_staged_lines() -> Result<Vec<(PathBuf, u32, String)>> {
+    let repo = Repository::open(".")?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None)?;
+
+    let mut lines = Vec::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _, line| {
+            if let (Some(path), Some(lineno), '+') =
+                (delta.new_file().path(), line.new_lineno(), line.origin())
+            {
+                let content = String::from_utf8_lossy(line.content());
+                lines.push((
+                    path.to_owned(),
+                    lineno,
+                    content.trim_end_matches(['\r', '\n']).to_owned(),
+                ));
+            }
+            true
+        }),
+    )?;
+    Ok(lines)
+}
+
+/// Lines added or modified since `base` in the working tree, including staged changes, by absolute file path.
+///
+/// Without a `base`, the upstream of the current branch is used, falling back to `HEAD`.
+pub fn get_changed_lines(base: Option<&str>) -> Result<BTreeMap<PathBuf, BTreeSet<u32>>> {
+    let repo = Repository::open(".")?;
+    let root = repo.workdir().map(PathBuf::from).unwrap_or_default();
+    let base = match base {
+        Some(base) => Some(repo.revparse_single(base)?),
+        None => repo
+            .revparse_single("@{upstream}")
+            .or_else(|_| repo.revparse_single("HEAD"))
+            .ok(),
+    };
+    let tree = base.map(|base| base.peel_to_tree()).transpose()?;
+    let diff = repo.diff_tree_to_workdir_with_index(tree.as_ref(), None)?;
+
+    let mut changed: BTreeMap<PathBuf, BTreeSet<u32>> = BTreeMap::new();
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |delta, _, line| {
+            if let (Some(path), Some(lineno), '+') =
+                (delta.new_file().path(), line.new_lineno(), line.origin())
+            {
+                changed.entry(root.join(path)).or_default().insert(lineno);
+            }
+            true
+        }),
+    )?;
+    Ok(changed)
+}
+
+/// The git status of a file, given its absolute path.
+pub fn get_file_status(path: &Path) -> Result<Status> {
+    let repo = Repository::open(".")?;
+    let root = repo.workdir().map(PathBuf::from).unwrap_or_default();
+    let relative = path.strip_prefix(&root).unwrap_or(path);
+    Ok(repo.status_file(relative)?)
+}
+
+/// A file, as staged or as of a commit.
+pub struct GitBlob {
+    pub id: Oid,
+    /// Commit that added or modified the file, `None` if it's staged
+    pub commit: Option<Oid>,
+    /// Path relative to the repository root
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Files added or modified by a diff, skipping submodules.
+///
+/// Sizes come from object headers, so contents aren't read.
+fn diff_blobs(repo: &Repository, diff: &Diff<'_>, commit: Option<Oid>) -> Result<Vec<GitBlob>> {
+    let odb = repo.odb()?;
+    let mut blobs = Vec::new();
+    for delta in diff.deltas() {
+        let file = delta.new_file();
+        // Submodules' ids are commits of another repository, so they aren't in the object database
+        if delta.status() == Delta::Deleted || file.mode() == FileMode::Commit {
+            continue;
+        }
+        let Some(path) = file.path() else {
+            continue;
+        };
+        let (size, kind) = odb.read_header(file.id())?;
+        if kind == ObjectType::Blob {
+            blobs.push(GitBlob {
+                id: file.id(),
+                commit,
+                path: path.to_owned(),
+                size: size as u64,
+            });
+        }
+    }
+    Ok(blobs)
+}
+
+/// Contents of blobs, in the same order.
+pub fn read_blobs(blobs: &[&GitBlob]) -> Result<Vec<Vec<u8>>> {
+    let repo = Repository::open(".")?;
+    blobs
+        .iter()
+        .map(|blob| Ok(repo.find_blob(blob.id)?.content().to_vec()))
+        .collect()
+}
+
+/// Staged files added or modified compared to `HEAD`.
+pub fn get_staged_blobs() -> Result<Vec<GitBlob>> {
+    let repo = Repository::open(".")?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let diff = repo.diff_tree_to_index(head.as_ref(), None, None)?;
+    diff_blobs(&repo, &diff, None)
+}
+
+/// Local and remote commits of each ref being pushed, which git passes to the `pre-push` hook on stdin.
+fn pushed_refs() -> &'static [(Oid, Oid)] {
+    static REFS: OnceLock<Vec<(Oid, Oid)>> = OnceLock::new();
+    REFS.get_or_init(|| {
+        // `cargo preflight run --hook push` isn't given any refs, and stdin may never be closed
+        let is_hook = env::args_os()
+            .next()
+            .is_some_and(|arg| Path::new(&arg).file_name() == Some(OsStr::new("pre-push")));
+        if !is_hook {
+            return vec![];
+        }
+        stdin()
+            .lines()
+            .map_while(std::io::Result::ok)
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split_whitespace().collect();
+                let [_, local, _, remote] = fields[..] else {
+                    return None;
+                };
+                Some((Oid::from_str(local).ok()?, Oid::from_str(remote).ok()?))
+            })
+            .collect()
+    })
+}
+
+/// Commits being pushed, newest first.
+///
+/// Outside of the `pre-push` hook, these are the commits on `HEAD` that aren't on any remote branch.
+fn get_pushed_commits(repo: &Repository) -> Result<Vec<Oid>> {
+    let mut walk = repo.revwalk()?;
+    let refs = pushed_refs();
+    if refs.is_empty() {
+        walk.push_head()?;
+        walk.hide_glob("refs/remotes")?;
+    }
+    for (local, remote) in refs {
+        // A zero local commit deletes the remote ref, so nothing is pushed
+        if local.is_zero() {
+            continue;
+        }
+        walk.push(*local)?;
+        // A new remote ref, or one whose commit isn't fetched, is compared to all remote branches
+        if remote.is_zero() || walk.hide(*remote).is_err() {
+            walk.hide_glob("refs/remotes")?;
+        }
+    }
+    Ok(walk.collect::<Result<_, _>>()?)
+}
+
+/// Files added or modified by the commits being pushed, each blob only once.
+pub fn get_pushed_blobs() -> Result<Vec<GitBlob>> {
+    let repo = Repository::open(".")?;
+    let mut seen = BTreeSet::new();
+    let mut blobs = Vec::new();
+    for oid in get_pushed_commits(&repo)? {
+        let commit = repo.find_commit(oid)?;
+        let tree = commit.tree()?;
+        // Merges are diffed against every parent, so changes made while resolving conflicts are included
+        let parents = commit
+            .parents()
+            .map(|parent| parent.tree().map(Some))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parents = if parents.is_empty() {
+            vec![None]
+        } else {
+            parents
+        };
+        for parent in &parents {
+            let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&tree), None)?;
+            blobs.extend(
+                diff_blobs(&repo, &diff, Some(oid))?
+                    .into_iter()
+                    .filter(|blob| seen.insert(blob.id)),
+            );
+        }
+    }
+    Ok(blobs)
+}
+
+/// Messages of the commits being pushed.
+pub fn get_pushed_messages() -> Result<Vec<(Oid, String)>> {
+    let repo = Repository::open(".")?;
+    get_pushed_commits(&repo)?
+        .into_iter()
+        .map(|oid| {
+            let commit = repo.find_commit(oid)?;
+            Ok((
+                oid,
+                String::from_utf8_lossy(commit.message_bytes()).into_owned(),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use cargo_metadata::{MetadataCommand, Package};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use crate::git::get_staged_files;
+
+/// Files in the workspace root that can change how every package builds, as can anything in `.cargo/`.
+const WORKSPACE_FILES: [&str; 4] = [
+    "Cargo.toml",
+    "Cargo.lock",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+];
+
+/// The workspace package containing `path`, preferring the most deeply nested one.
+fn owning_package<'a>(packages: &[&'a Package], path: &Path) -> Option<&'a Package> {
+    packages
+        .iter()
+        .filter_map(|package| {
+            let root = package.manifest_path.parent()?;
+            path.starts_with(root)
+                .then_some((root.as_str().len(), *package))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, package)| package)
+}
+
+/// Workspace packages depending on any of `changed`, directly or through other workspace packages.
+fn with_dependents(packages: &[&Package], changed: BTreeSet<String>) -> BTreeSet<String> {
+    let mut affected = changed;
+    loop {
+        let dependents: Vec<String> = packages
+            .iter()
+            .filter(|package| !affected.contains(package.name.as_str()))
+            .filter(|package| {
+                package
+                    .dependencies
+                    .iter()
+                    .any(|dep| dep.path.is_some() && affected.contains(&dep.name))
+            })
+            .map(|package| package.name.to_string())
+            .collect();
+        if dependents.is_empty() {
+            return affected;
+        }
+        affected.extend(dependents);
+    }
+}
+
+/// Arguments selecting the tests affected by staged changes, one set per test run, or `None` if the whole suite needs to run.
+///
+/// Changed packages are tested along with the workspace packages depending on them. Packages where only
+/// integration test files changed just run those tests.
+pub fn impacted_tests() -> Result<Option<Vec<Vec<String>>>> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let packages = metadata.workspace_packages();
+
+    let mut changed = BTreeSet::new();
+    let mut changed_tests: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let root = metadata.workspace_root.as_std_path();
+    for path in get_staged_files()? {
+        let affects_builds = path.starts_with(root.join(".cargo"))
+            || (path.parent() == Some(root)
+                && path
+                    .file_name()
+                    .is_some_and(|name| WORKSPACE_FILES.iter().any(|file| name == *file)));
+        if affects_builds {
+            return Ok(None);
+        }
+        let Some(package) = owning_package(&packages, &path) else {
+            continue;
+        };
+        let test_target = package
+            .targets
+            .iter()
+            .find(|target| target.is_test() && target.src_path.as_std_path() == path);
+        if let Some(target) = test_target {
+            changed_tests
+                .entry(package.name.to_string())
+                .or_default()
+                .insert(target.name.clone());
+        } else {
+            changed.insert(package.name.to_string());
+        }
+    }
+
+    let affected = with_dependents(&packages, changed);
+    let mut selections = Vec::new();
+    if !affected.is_empty() {
+        selections.push(
+            affected
+                .iter()
+                .flat_map(|package| ["-p".to_owned(), package.clone()])
+                .collect(),
+        );
+    }
+    for (package, tests) in changed_tests {
+        if affected.contains(&package) {
+            continue;
+        }
+        let mut args = vec!["-p".to_owned(), package];
+        args.extend(
+            tests
+                .into_iter()
+                .flat_map(|test| ["--test".to_owned(), test]),
+        );
+        selections.push(args);
+    }
+    Ok(Some(selections))
+}
@@ -0,0 +1,349 @@
+use anyhow::Result;
+use cargo_metadata::{MetadataCommand, Package, semver::VersionReq};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+use crate::{config::LicensesConfig, error::PreflightError};
+
+/// Split an SPDX expression into identifiers, operators and parentheses.
+///
+/// The legacy `MIT/Apache-2.0` form is read as `MIT OR Apache-2.0`.
+fn tokenize(expression: &str) -> Vec<String> {
+    expression
+        .replace('/', " OR ")
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// Evaluates an SPDX license expression against the allow and deny lists.
+struct Evaluator<'a> {
+    cfg: &'a LicensesConfig,
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Evaluator<'_> {
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(String::as_str)
+    }
+
+    /// Whether a single license, such as `GPL-2.0+` or `Apache-2.0 WITH LLVM-exception`, is acceptable.
+    fn accepts(&self, license: &str, exception: Option<&str>) -> bool {
+        let base = license.trim_end_matches('+');
+        let with_exception = exception.map(|exception| format!("{license} WITH {exception}"));
+        let matches = |list: &[String]| {
+            list.iter().any(|entry| {
+                entry == license || entry == base || Some(entry) == with_exception.as_ref()
+            })
+        };
+        !matches(&self.cfg.deny) && (self.cfg.allow.is_empty() || matches(&self.cfg.allow))
+    }
+
+    /// `or_expression = and_expression ("OR" and_expression)*`
+    fn or_expression(&mut self) -> Option<bool> {
+        let mut accepted = self.and_expression()?;
+        while self.peek() == Some("OR") {
+            self.next();
+            accepted |= self.and_expression()?;
+        }
+        Some(accepted)
+    }
+
+    /// `and_expression = term ("AND" term)*`
+    fn and_expression(&mut self) -> Option<bool> {
+        let mut accepted = self.term()?;
+        while self.peek() == Some("AND") {
+            self.next();
+            accepted &= self.term()?;
+        }
+        Some(accepted)
+    }
+
+    /// `term = "(" or_expression ")" | license ("WITH" exception)?`
+    fn term(&mut self) -> Option<bool> {
+        let token = self.next()?.to_owned();
+        if token == "(" {
+            let accepted = self.or_expression()?;
+            return (self.next() == Some(")")).then_some(accepted);
+        }
+        if matches!(token.as_str(), ")" | "AND" | "OR" | "WITH") {
+            return None;
+        }
+        let exception = if self.peek() == Some("WITH") {
+            self.next();
+            Some(self.next()?.to_owned())
+        } else {
+            None
+        };
+        Some(self.accepts(&token, exception.as_deref()))
+    }
+}
+
+/// Whether an SPDX license expression is acceptable, or `None` if it can't be parsed.
+fn license_accepted(cfg: &LicensesConfig, expression: &str) -> Option<bool> {
+    let mut evaluator = Evaluator {
+        cfg,
+        tokens: tokenize(expression),
+        position: 0,
+    };
+    let accepted = evaluator.or_expression()?;
+    (evaluator.position == evaluator.tokens.len()).then_some(accepted)
+}
+
+fn license_issue(cfg: &LicensesConfig, package: &Package) -> Option<String> {
+    if cfg
+        .license_exceptions
+        .iter()
+        .any(|name| name == package.name.as_str())
+    {
+        return None;
+    }
+    let Some(license) = &package.license else {
+        return Some(format!(
+            "{} {} has no SPDX license expression",
+            package.name, package.version
+        ));
+    };
+    match license_accepted(cfg, license) {
+        Some(true) => None,
+        Some(false) => Some(format!(
+            "{} {} uses license `{license}`, which isn't allowed",
+            package.name, package.version
+        )),
+        None => Some(format!(
+            "{} {} has an invalid license expression `{license}`",
+            package.name, package.version
+        )),
+    }
+}
+
+fn is_banned(cfg: &LicensesConfig, package: &Package) -> bool {
+    cfg.banned.iter().any(|ban| match ban.split_once('@') {
+        Some((name, requirement)) => {
+            name == package.name.as_str()
+                && VersionReq::parse(requirement).is_ok_and(|req| req.matches(&package.version))
+        }
+        None => ban == package.name.as_str(),
+    })
+}
+
+fn source_issue(cfg: &LicensesConfig, package: &Package) -> Option<String> {
+    let source = package.source.as_ref()?.repr.as_str();
+    let trim = |url: &str| url.trim_end_matches('/').to_owned();
+    let allowed = match source.split_once('+') {
+        Some(("git", url)) => cfg
+            .allow_git
+            .iter()
+            .any(|prefix| url.starts_with(prefix.as_str())),
+        Some(("registry" | "sparse", url)) => cfg
+            .allow_registries
+            .iter()
+            .any(|registry| trim(registry) == trim(url)),
+        _ => false,
+    };
+    (!allowed).then(|| {
+        format!(
+            "{} {} comes from `{source}`, which isn't an allowed source",
+            package.name, package.version
+        )
+    })
+}
+
+pub fn licenses(cfg: &LicensesConfig) -> Result<()> {
+    let metadata = MetadataCommand::new()
+        .other_options(vec!["--offline".to_owned()])
+        .exec()?;
+    let dependencies: Vec<&Package> = metadata
+        .packages
+        .iter()
+        .filter(|package| !metadata.workspace_members.contains(&package.id))
+        .collect();
+
+    let mut issues = Vec::new();
+    let mut versions: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for package in &dependencies {
+        issues.extend(license_issue(cfg, package));
+        if is_banned(cfg, package) {
+            issues.push(format!("{} {} is banned", package.name, package.version));
+        }
+        issues.extend(source_issue(cfg, package));
+        versions
+            .entry(package.name.as_str())
+            .or_default()
+            .push(package.version.to_string());
+    }
+    if let Some(max_versions) = cfg.max_versions {
+        for (name, versions) in versions {
+            if versions.len() > max_versions && !cfg.allow_duplicates.iter().any(|n| n == name) {
+                issues.push(format!(
+                    "{name} has {} versions ({}), more than the {max_versions} allowed",
+                    versions.len(),
+                    versions.join(", ")
+                ));
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        println!(
+            "    {}",
+            format!(
+                "[√] Licenses preflight check passed ({} dependencies)",
+                dependencies.len()
+            )
+            .green()
+        );
+        Ok(())
+    } else {
+        Err(PreflightError::LicensesFailed {
+            license_issues: issues
+                .iter()
+                .map(|issue| format!("        - {issue}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(allow: &[&str], deny: &[&str]) -> LicensesConfig {
+        LicensesConfig {
+            allow: allow.iter().map(ToString::to_string).collect(),
+            deny: deny.iter().map(ToString::to_string).collect(),
+            ..LicensesConfig::default()
+        }
+    }
+
+    fn package(name: &str, version: &str, source: Option<&str>) -> Package {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "version": version,
+            "id": format!("{name} {version}"),
+            "source": source,
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": format!("/{name}/Cargo.toml"),
+        }))
+        .expect("package metadata is valid")
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let cfg = config(&["MIT"], &[]);
+        assert_eq!(
+            license_accepted(&cfg, "MIT OR GPL-3.0 AND Zlib"),
+            Some(true)
+        );
+        assert_eq!(
+            license_accepted(&cfg, "GPL-3.0 AND Zlib OR MIT"),
+            Some(true)
+        );
+        assert_eq!(
+            license_accepted(&cfg, "(MIT OR GPL-3.0) AND Zlib"),
+            Some(false)
+        );
+        assert_eq!(license_accepted(&cfg, "MIT AND (Zlib OR MIT)"), Some(true));
+    }
+
+    #[test]
+    fn accepts_exceptions_and_later_versions() {
+        let cfg = config(&["Apache-2.0 WITH LLVM-exception", "GPL-2.0"], &[]);
+        assert_eq!(
+            license_accepted(&cfg, "Apache-2.0 WITH LLVM-exception"),
+            Some(true)
+        );
+        assert_eq!(
+            license_accepted(&cfg, "Apache-2.0 WITH Classpath-exception-2.0"),
+            Some(false)
+        );
+        assert_eq!(license_accepted(&cfg, "Apache-2.0"), Some(false));
+        assert_eq!(license_accepted(&cfg, "GPL-2.0+"), Some(true));
+    }
+
+    #[test]
+    fn reads_legacy_slash_expressions() {
+        let cfg = config(&["Apache-2.0"], &[]);
+        assert_eq!(license_accepted(&cfg, "MIT/Apache-2.0"), Some(true));
+        assert_eq!(license_accepted(&cfg, "MIT/GPL-3.0"), Some(false));
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        let cfg = config(&[], &[]);
+        for expression in [
+            "",
+            "MIT OR",
+            "AND MIT",
+            "(MIT",
+            "MIT)",
+            "MIT Apache-2.0",
+            "Apache-2.0 WITH",
+        ] {
+            assert_eq!(license_accepted(&cfg, expression), None, "{expression}");
+        }
+    }
+
+    #[test]
+    fn deny_overrides_allow() {
+        let cfg = config(&["MIT", "GPL-3.0"], &["GPL-3.0"]);
+        assert_eq!(license_accepted(&cfg, "GPL-3.0"), Some(false));
+        assert_eq!(license_accepted(&cfg, "GPL-3.0 OR MIT"), Some(true));
+        assert_eq!(
+            license_accepted(&config(&[], &["GPL-3.0"]), "GPL-3.0+"),
+            Some(false)
+        );
+        assert_eq!(
+            license_accepted(&config(&[], &["GPL-3.0"]), "Zlib"),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn bans_by_name_and_version_requirement() {
+        let cfg = LicensesConfig {
+            banned: vec!["openssl".to_owned(), "time@<0.2".to_owned()],
+            ..LicensesConfig::default()
+        };
+        assert!(is_banned(&cfg, &package("openssl", "0.10.0", None)));
+        assert!(is_banned(&cfg, &package("time", "0.1.45", None)));
+        assert!(!is_banned(&cfg, &package("time", "0.3.0", None)));
+        assert!(!is_banned(&cfg, &package("openssl-sys", "0.9.0", None)));
+    }
+
+    #[test]
+    fn matches_allowed_sources() {
+        let cfg = LicensesConfig {
+            allow_git: vec!["https://github.com/org/".to_owned()],
+            ..LicensesConfig::default()
+        };
+        let issue = |source| source_issue(&cfg, &package("dep", "1.0.0", source));
+        assert_eq!(issue(None), None);
+        assert_eq!(
+            issue(Some(
+                "registry+https://github.com/rust-lang/crates.io-index"
+            )),
+            None
+        );
+        assert_eq!(issue(Some("sparse+https://index.crates.io")), None);
+        assert_eq!(
+            issue(Some("git+https://github.com/org/dep?branch=main#0123abc")),
+            None
+        );
+        assert!(issue(Some("git+https://github.com/other/dep#0123abc")).is_some());
+        assert!(issue(Some("sparse+https://registry.example.com/index/")).is_some());
+    }
+}
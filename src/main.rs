@@ -74,9 +74,12 @@
 //!
 //! ```toml
 //! [[preflight]] # Create new table entry for different behaviours
+//! name = "quick" # Optional name, used to select this entry with `cargo preflight run --entry`
+//!
 //! run_when = [
 //!     "commit",
 //!     "push",
+//!     "publish", # Run by `cargo preflight publish`
 //! ] # Default values: ["push"]
 //!
 //! # List of branch names to run on, below is an example.
@@ -90,17 +93,154 @@
 //! checks = [
 //!     "fmt", # `cargo fmt -- --check`
 //!     "clippy", # `cargo clippy -- -D warnings`
-//!     "test", # `cargo test`
+//!     "test", # `cargo test`, or `cargo nextest run` if configured
+//!     "coverage", # `cargo test --tests` with LLVM coverage, needs `rustup component add llvm-tools`
 //!     "check_tests", # `cargo check --tests`
 //!     "unused_deps", # uses `cargo-shear`
 //!     "secrets", # uses `ripsecrets`
 //!     "check_examples",  # `cargo check --examples`
 //!     "check_benches", # `cargo check --benches`
+//!     "doc", # `cargo doc --no-deps`, with `RUSTDOCFLAGS="-D warnings"`
+//!     "doctest", # `cargo test --doc`
+//!     "features", # `cargo check` for each feature combination
+//!     "msrv", # `cargo +<rust-version> check`, using a toolchain already installed with rustup
+//!     "semver", # uses `cargo-semver-checks`, which must be installed
+//!     "publish_dry_run", # `cargo publish --dry-run`
+//!     "package", # checks the files listed by `cargo package --list`
+//!     "clean_tree", # no uncommitted changes or untracked files
+//!     "version_tag", # a `v<version>`, `<version>` or `<name>-v<version>` tag exists for each package
+//!     "licenses", # dependency licenses, bans, duplicates and sources from `cargo metadata --offline`
+//!     "audit", # `Cargo.lock` against a local RustSec advisory-db checkout, and yanked crates
+//!     "lockfile", # `Cargo.lock` is committed, current (`cargo metadata --locked --offline`) and staged with manifests
+//!     "forbidden_patterns", # Lines added by staged changes don't match forbidden patterns, such as `dbg!` or conflict markers
+//!     "file_size", # Staged files (or pushed files, on push) aren't too large or forbidden, such as keys or databases
 //! ] # Default values: ["fmt", "test"]
 //!
-//! autofix = false # Enables autofix functionality (for fmt and clippy)
+//! autofix = false # Enables autofix functionality (for fmt, clippy and unused_deps)
 //!
 //! over_ride = false # Enables override functionality
+//!
+//! # What to do with a failed check when Preflight can't prompt (in CI, GUI git clients, or with `--no-input`).
+//! # One of "fail", "autofix" or "override".
+//! on_failure = "fail" # Default value: "fail"
+//!
+//! # Options for cargo-based checks (fmt, clippy, test, check_tests, check_examples, check_benches).
+//! [preflight.options.clippy]
+//! args = ["--workspace", "--all-features"] # Extra cargo arguments
+//! lint_args = ["-D", "warnings"] # Arguments after `--`, replacing the check's defaults (fmt always keeps `--check`)
+//! env = { RUSTFLAGS = "-C target-cpu=native" } # Environment variables to set
+//! target = "x86_64-unknown-linux-musl" # Target triple to build for (unused by fmt)
+//! toolchain = "nightly" # Rustup toolchain to run the check with
+//!
+//! # Run a check once per toolchain and target, showing the results as a table. Toolchains and
+//! # targets must already be installed with rustup. Supported by every check using the options above
+//! # except fmt, and by doc and doctest.
+//! [preflight.options.test]
+//! toolchains = ["stable", "nightly", "1.75"]
+//! targets = ["x86_64-unknown-linux-musl", "wasm32-unknown-unknown"]
+//!
+//! # Options for the features check, which runs `cargo check` for each workspace package with
+//! # `--no-default-features`, `--all-features`, and every combination of up to `depth` features.
+//! # With `exclude`, every other feature is enabled instead of `--all-features`.
+//! [preflight.features]
+//! depth = 1 # Default value: 1, checking each feature on its own
+//! exclude = ["nightly"] # Features to leave out of combinations and the all features run. Default values: []
+//!
+//! # Options for the semver check, which fails when breaking changes to the public API don't match
+//! # the version bump in `Cargo.toml`. Without a baseline, the latest tag before `HEAD` is used.
+//! [preflight.semver]
+//! baseline_rev = "v1.2.0" # Git revision to compare against
+//! baseline_crate = "/path/to/my_crate-1.2.0.crate" # Local `.crate` file to compare against instead
+//!
+//! # Options for the package check, which fails if packaged files include large files, secrets or
+//! # `target/` artifacts, or are missing the `readme` or `license-file` set in `Cargo.toml`.
+//! # Secrets are found with the patterns, allowlist and baseline under `[preflight.secrets]`.
+//! [preflight.package]
+//! max_file_size = 5242880 # Largest packaged file allowed, in bytes. Default value: 5242880 (5 MiB)
+//! allow_large_files = ["tests/fixtures/large.bin"] # Files allowed to be larger. Default values: []
+//!
+//! # Options for the doc check. Setting `RUSTDOCFLAGS` in `[preflight.options.doc.env]` replaces these.
+//! [preflight.doc]
+//! missing_docs = false # Also deny missing docs on public items. Default value: false
+//!
+//! # Options for the test check, which only reports failed tests and their output.
+//! [preflight.test]
+//! runner = "nextest" # "cargo" or "nextest", falling back to `cargo test` if nextest isn't installed. Default value: "cargo"
+//! profile = "ci" # Nextest profile to run with
+//! retries = 2 # Times a failed test is rerun, reporting it as flaky if it then passes. Default value: 0
+//! quarantine = ["tests::slow_network"] # Tests whose failures are reported without failing the check. Default values: []
+//! # On commit, only test packages with staged changes and the workspace packages depending on them, or just
+//! # the touched integration test files. Changes to the workspace `Cargo.toml`, `Cargo.lock` or `.cargo/`
+//! # run the whole suite, as does every other hook. Default value: false
+//! impact = true
+//!
+//! # Options for the coverage check. Changed lines are those added or modified in the working tree and
+//! # index since `base_rev`, and only lines with instrumented code count towards coverage.
+//! [preflight.coverage]
+//! min_total = 0.0 # Lowest line coverage allowed for the workspace, as a percentage. Default value: 0.0
+//! min_changed = 80.0 # Lowest line coverage allowed for changed lines, as a percentage. Default value: 80.0
+//! base_rev = "origin/main" # Defaults to the current branch's upstream, or `HEAD` if there isn't one
+//!
+//! # Options for the licenses check, which applies to every dependency but not workspace packages.
+//! # License expressions such as `MIT OR Apache-2.0` pass if any alternative is allowed and not denied.
+//! [preflight.licenses]
+//! allow = ["MIT", "Apache-2.0", "Apache-2.0 WITH LLVM-exception"] # Any license that isn't denied if empty. Default values: []
+//! deny = ["GPL-3.0"] # Default values: []
+//! license_exceptions = ["ring"] # Crates exempt from license checks. Default values: []
+//! banned = ["openssl", "time@<0.2"] # Crates that may not be used, optionally with a version requirement. Default values: []
+//! max_versions = 1 # Most versions of a single crate allowed. Default: no limit
+//! allow_duplicates = ["syn"] # Crates allowed more than `max_versions` versions. Default values: []
+//! allow_registries = ["https://github.com/rust-lang/crates.io-index", "https://index.crates.io/"] # Default values: crates.io
+//! allow_git = ["https://github.com/my-org/"] # URL prefixes of allowed git dependencies. Default values: []
+//!
+//! # Options for the audit check, which works offline. Keep the advisory database up to date with `git pull`,
+//! # and yanked crates are found in the registry index cache cargo keeps in `~/.cargo/registry/index`.
+//! [preflight.audit]
+//! db_path = "/path/to/advisory-db" # Default value: "~/.cargo/advisory-db"
+//! deny_yanked = true # Fail on yanked crates. Default value: true
+//! deny_informational = false # Fail on unmaintained or unsound crates, rather than warning. Default value: false
+//!
+//! [[preflight.audit.ignore]]
+//! id = "RUSTSEC-2023-0071"
+//! until = 2025-06-30 # Ignored up to and including this date, optional
+//! reason = "No fix available, and we don't use the affected API" # Optional
+//!
+//! # Options for the unused_deps check, which lists unused dependencies in a table per crate.
+//! [preflight.shear]
+//! packages = ["my_crate"] # Workspace packages to check. Default values: [] (all of them)
+//! exclude = ["my_crate_macros"] # Workspace packages not to check, whose workspace dependencies count as used. Default values: []
+//! ignored = ["serde"] # Unused dependencies to ignore, on top of `[package.metadata.cargo-shear]`. Default values: []
+//! expand = false # Expand macros with `cargo expand` first, which requires nightly. Default value: false
+//!
+//! # Options for the secrets check, which reports each secret's location and fingerprint, with its value redacted.
+//! [preflight.secrets]
+//! mode = "changes" # "tree" searches files on disk, "changes" only staged files on commit and the pushed commits' files on push. Default value: "tree"
+//! commit_messages = true # Also search the messages of commits being pushed. Default value: false
+//! paths = ["."] # Paths to search, respecting `.gitignore` and `.secretsignore` for files on disk. Default values: ["."]
+//! additional_patterns = ["MYCO-[0-9a-f]{16}"] # Extra regexes to search for. Default values: []
+//! allowlist = ["4054d096d429f0ff"] # Fingerprints of known false positives. Default values: []
+//! baseline = ".secrets-baseline" # File of existing secrets' fingerprints, which don't fail the check
+//!
+//! # Options for the forbidden_patterns check, which only looks at lines added by staged changes.
+//! # Built-in rules: dbg, todo, unimplemented, println (in library targets' source files, outside binary targets),
+//! # ignore_without_reason (`#[ignore]` rather than `#[ignore = "reason"]`) and conflict_marker (`=======` only between
+//! # `<<<<<<<` and `>>>>>>>`).
+//! [preflight.forbidden_patterns]
+//! disable = ["println"] # Built-in rules to turn off, unknown names are an error. Default values: []
+//!
+//! [[preflight.forbidden_patterns.rules]]
+//! name = "fixme"
+//! pattern = "FIXME" # Regex matched against each added line
+//! paths = ["src/**"] # Globs of files the rule applies to. Default values: [] (all files)
+//! exclude = ["src/generated/**"] # Globs of files it doesn't apply to. Default values: []
+//! severity = "warning" # "error" fails the check, "warning" only reports it. Default value: "error"
+//! message = "Open an issue instead" # Shown with each match, optional
+//!
+//! # Options for the file_size check, which reads sizes from git rather than the working tree.
+//! [preflight.file_size]
+//! max_size = 1048576 # Largest file allowed, in bytes. Default value: 1048576 (1 MiB)
+//! forbidden = ["*.pem", "*.sqlite"] # Globs of files that shouldn't be committed. Default values: ["*.pem", "*.key", "*.p12", "*.pfx", "*.sqlite", "*.sqlite3", "*.db"]
+//! allow = ["tests/fixtures/**"] # Globs of files exempt from both limits. Default values: []
 //! ```
 //!
 //! ## Example Config:
@@ -141,17 +281,62 @@
 //! cargo preflight --init
 //! ```
 //!
+//! Preflight can also be run as a one-off test with the `cargo preflight` command.
+//!
+//! To reproduce exactly what a git hook would do without committing or pushing, use `cargo preflight run`:
+//!
+//! ```sh
+//! cargo preflight run --hook push # Only run entries with `run_when` containing "push"
+//! cargo preflight run --check clippy # Only run the clippy check
+//! cargo preflight run --entry quick # Only run the entry named "quick" (or by index, starting at 0)
+//! cargo preflight run --hook push --branch main # Apply branch rules as if on `main`
+//! cargo preflight run --hook push --dry-run # Print the checks and exact commands that would run, without running them
+//! ```
+//!
+//! Preflight only prompts to autofix or override failed checks when it has a terminal and isn't running in CI (the `CI` environment variable is set).
+//! Otherwise, or when run with `--no-input`, the `on_failure` policy is applied instead.
+//! `--yes` answers yes to every autofix and override prompt enabled in config.
+//!
+//! ## Secrets Baseline
+//!
+//! `cargo preflight secrets-baseline` records the secrets found now in the `baseline` file set under `[preflight.secrets]`, so existing findings don't block while new ones do:
+//!
+//! ```sh
+//! cargo preflight secrets-baseline
+//! ```
+//!
+//! ## Publishing
+//!
+//! `cargo preflight publish` runs the checks configured to run on `publish`, and only if they pass runs `cargo publish` with the same arguments:
+//!
+//! ```sh
+//! cargo preflight publish -- --registry my-registry
+//! ```
+//!
 //! _Note: Currently, Preflight only supports Linux systems._
 //!
 
+mod audit;
 mod autocomplete;
 mod checks;
 mod cli;
 mod config;
+mod coverage;
 mod error;
+mod features;
+mod file_size;
 mod fix;
+mod forbidden;
 mod git;
+mod impact;
+mod licenses;
+mod package;
 mod preflight;
+mod secrets;
+mod semver;
+mod shear;
+mod testing;
+mod toolchain;
 mod util;
 
 use std::env;
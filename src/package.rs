@@ -0,0 +1,138 @@
+use anyhow::Result;
+use cargo_metadata::{MetadataCommand, Package};
+use colored::Colorize;
+use std::{fs, path::PathBuf, process::Command};
+
+use crate::{
+    checks::{PACKAGE_ARGS, cargo_command},
+    config::{CheckOptions, PackageConfig, SecretsConfig},
+    error::PreflightError,
+    secrets::find_secrets_in,
+};
+
+/// The `cargo package --list` command for each publishable workspace package.
+pub fn package_commands(options: &CheckOptions) -> Result<Vec<(Package, Command)>> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    Ok(metadata
+        .workspace_packages()
+        .into_iter()
+        .filter(|package| {
+            package
+                .publish
+                .as_ref()
+                .is_none_or(|registries| !registries.is_empty())
+        })
+        .map(|package| {
+            let cmd = cargo_command(
+                &PACKAGE_ARGS,
+                options,
+                &["-p".to_owned(), package.name.to_string()],
+            );
+            (package.clone(), cmd)
+        })
+        .collect())
+}
+
+/// Problems with the files packaged for `package`, listed by `cargo package --list`.
+fn package_issues(
+    cfg: &PackageConfig,
+    secrets: &SecretsConfig,
+    package: &Package,
+    files: &[&str],
+) -> Result<Vec<String>> {
+    let root = package
+        .manifest_path
+        .parent()
+        .map_or_else(PathBuf::new, |root| root.as_std_path().to_owned());
+    let mut issues = Vec::new();
+
+    // Referenced files outside the package root are packaged under their file name
+    let referenced = [
+        ("readme", &package.readme),
+        ("license-file", &package.license_file),
+    ];
+    for (field, path) in referenced {
+        if let Some(path) = path
+            && !files
+                .iter()
+                .any(|file| *file == path.as_str() || Some(*file) == path.file_name())
+        {
+            issues.push(format!("`{field}` {path} is not included"));
+        }
+    }
+
+    let mut on_disk = Vec::new();
+    for file in files {
+        if file.starts_with("target/") || file.contains("/target/") {
+            issues.push(format!("{file} is a build artifact"));
+        }
+        let path = root.join(file);
+        // Files generated by cargo, such as `Cargo.toml.orig`, don't exist on disk
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.len() > cfg.max_file_size && !cfg.allow_large_files.iter().any(|f| f == file) {
+            issues.push(format!(
+                "{file} is {} bytes, larger than the {} byte limit",
+                metadata.len(),
+                cfg.max_file_size
+            ));
+        }
+        on_disk.push(path);
+    }
+
+    if !on_disk.is_empty() {
+        let secrets = find_secrets_in(secrets, &on_disk)?;
+        if !secrets.is_empty() {
+            issues.push(format!(
+                "Found {} secret(s) in packaged files:\n{}",
+                secrets.len(),
+                secrets
+                    .iter()
+                    .map(|secret| format!("            - {secret}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+    }
+    Ok(issues)
+}
+
+pub fn package(cfg: &PackageConfig, secrets: &SecretsConfig, options: &CheckOptions) -> Result<()> {
+    let mut failed = Vec::new();
+    for (package, mut cmd) in package_commands(options)? {
+        let output = cmd.output()?;
+        if !output.status.success() {
+            failed.push(format!(
+                "    {}\n{}",
+                package.name.bold(),
+                String::from_utf8_lossy(&output.stderr)
+            ));
+            continue;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let files: Vec<&str> = stdout.lines().collect();
+        let issues = package_issues(cfg, secrets, &package, &files)?;
+        if !issues.is_empty() {
+            failed.push(format!(
+                "    {}\n{}",
+                package.name.bold(),
+                issues
+                    .iter()
+                    .map(|issue| format!("        - {issue}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+    }
+
+    if failed.is_empty() {
+        println!("    {}", "[√] Package preflight check passed".green());
+        Ok(())
+    } else {
+        Err(PreflightError::PackageFailed {
+            package_issues: failed.join("\n"),
+        }
+        .into())
+    }
+}
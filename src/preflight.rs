@@ -1,39 +1,224 @@
 use anyhow::Result;
 use colored::Colorize;
+use std::process::Command;
 
 use crate::{
-    checks::{check_branch_rules, run_checks},
-    cli::{autofix_prompt, print_checklist, update_config},
-    config::{PreflightConfig, check_local_config},
-    error::failed_check_index,
-    fix::over_ride,
+    checks::{check_branch_rules, describe_check, describe_command, run_checks},
+    cli::{autofix_prompt, is_interactive, print_checklist, update_config},
+    config::{OnFailure, PreflightConfig, PreflightConfigWrapper, check_local_config},
+    error::{PreflightError, failed_check_index},
+    fix::{apply_autofix, over_ride, skip_check},
     git::{delete_symlink, init_symlink},
+    secrets::update_baseline,
 };
 
-const SUPPORTED_AUTOFIX: [&str; 2] = ["fmt", "clippy"];
+const SUPPORTED_AUTOFIX: [&str; 3] = ["fmt", "clippy", "unused_deps"];
 
-pub fn preflight_checks(cfg: &PreflightConfig, start: usize) -> Result<()> {
-    if !check_branch_rules(&cfg.branches) {
+/// How failed checks are handled when autofix or override is enabled.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    /// Ask before autofixing or overriding
+    #[default]
+    Prompt,
+    /// Autofix or override without asking (`--yes`)
+    AssumeYes,
+    /// Never prompt, applying the `on_failure` policy instead (`--no-input`, CI, or no terminal)
+    NoInput,
+}
+
+impl Interaction {
+    fn from_matches(matches: &clap::ArgMatches) -> Self {
+        if matches.get_flag("yes") {
+            Self::AssumeYes
+        } else if matches.get_flag("no-input") || !is_interactive() {
+            Self::NoInput
+        } else {
+            Self::Prompt
+        }
+    }
+}
+
+/// Which hook, config entries and checks a preflight run applies to.
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    pub hook: String,
+    pub checks: Vec<String>,
+    pub entry: Option<String>,
+    pub branch: Option<String>,
+    pub dry_run: bool,
+    pub interaction: Interaction,
+}
+
+impl RunOptions {
+    pub fn from_hook(matches: &clap::ArgMatches, hook: &str) -> Self {
+        Self {
+            hook: hook.to_owned(),
+            dry_run: matches.get_flag("dry-run"),
+            interaction: Interaction::from_matches(matches),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_matches(matches: &clap::ArgMatches, hook: &str) -> Self {
+        Self {
+            hook: matches
+                .get_one::<String>("hook")
+                .map_or_else(|| hook.to_owned(), ToOwned::to_owned),
+            checks: matches
+                .get_many::<String>("check")
+                .map(|checks| checks.cloned().collect())
+                .unwrap_or_default(),
+            entry: matches.get_one::<String>("entry").cloned(),
+            branch: matches.get_one::<String>("branch").cloned(),
+            dry_run: matches.get_flag("dry-run"),
+            interaction: Interaction::from_matches(matches),
+        }
+    }
+
+    /// Whether the config entry at `index` was selected with `--entry`, matching on its name first and then its index.
+    fn selects_entry(&self, index: usize, cfg: &PreflightConfig) -> bool {
+        self.entry.as_ref().is_none_or(|entry| {
+            cfg.name.as_ref() == Some(entry) || entry.parse::<usize>() == Ok(index)
+        })
+    }
+
+    /// Restrict the checks of a config entry to those selected with `--check`.
+    fn select_checks(&self, cfg: &PreflightConfig) -> PreflightConfig {
+        let mut selected = cfg.clone();
+        if !self.checks.is_empty() {
+            selected.checks.retain(|check| self.checks.contains(check));
+        }
+        selected
+    }
+}
+
+pub fn preflight_checks(cfg: &PreflightConfig, opts: &RunOptions, start: usize) -> Result<()> {
+    if !check_branch_rules(&cfg.branches, opts.branch.as_deref()) {
         println!("Branch not included in preflight checks, exiting...");
         return Ok(());
     }
-    let stopped_at = match run_checks(&cfg.checks[start..]) {
-        Ok(()) => None,
-        Err(e) => {
-            println!("{e:?}");
-            e.downcast_ref()
-                .and_then(|preflight_err| failed_check_index(&cfg.checks, preflight_err))
-        }
+    let Err(e) = run_checks(&cfg.checks[start..], cfg, &opts.hook) else {
+        return Ok(());
+    };
+    let Some(index) = e
+        .downcast_ref()
+        .and_then(|preflight_err| failed_check_index(&cfg.checks, preflight_err))
+    else {
+        return Err(e);
     };
+    println!("{e:?}");
+
+    let check = &cfg.checks[index];
+    let can_autofix = cfg.autofix && SUPPORTED_AUTOFIX.contains(&check.as_str());
+    match opts.interaction {
+        Interaction::Prompt if can_autofix => autofix_prompt(cfg, opts, index),
+        Interaction::Prompt if cfg.over_ride => over_ride(cfg, opts, index),
+        Interaction::AssumeYes if can_autofix => apply_autofix(cfg, opts, index),
+        Interaction::AssumeYes if cfg.over_ride => skip_check(cfg, opts, index),
+        Interaction::NoInput
+            if cfg.on_failure == OnFailure::Autofix
+                && SUPPORTED_AUTOFIX.contains(&check.as_str()) =>
+        {
+            apply_autofix(cfg, opts, index)
+        }
+        Interaction::NoInput if cfg.on_failure == OnFailure::Override => {
+            skip_check(cfg, opts, index)
+        }
+        _ => Err(PreflightError::CheckFailed {
+            failed_check: check.to_owned(),
+        }
+        .into()),
+    }
+}
 
-    if let (true, Some(index)) = (cfg.autofix, stopped_at) {
-        if SUPPORTED_AUTOFIX.contains(&cfg.checks[index].as_str()) {
-            autofix_prompt(cfg, index)?;
+/// Print the checks a config entry would run, and how, without executing anything.
+fn print_plan(cfg: &PreflightConfig, opts: &RunOptions) -> Result<()> {
+    println!(
+        "{} {:?}:",
+        cfg.name.as_deref().unwrap_or("(unnamed entry)").bold(),
+        cfg.run_when
+    );
+    if !check_branch_rules(&cfg.branches, opts.branch.as_deref()) {
+        println!("    Skipped, branch not included in {:?}", cfg.branches);
+        return Ok(());
+    }
+    println!(
+        "    autofix: {}, override: {}, on_failure: {} ({:?})",
+        cfg.autofix, cfg.over_ride, cfg.on_failure, opts.interaction
+    );
+    for check in &cfg.checks {
+        println!(
+            "    [ ] {check}: {}",
+            describe_check(check, cfg, &opts.hook)?
+        );
+    }
+    Ok(())
+}
+
+pub fn run_preflight(cfg: &PreflightConfigWrapper, opts: &RunOptions) -> Result<()> {
+    if opts.dry_run {
+        println!(
+            "{} (hook: {})",
+            "🛫 Preflight Plan, nothing will be executed".bold(),
+            opts.hook
+        );
+    } else {
+        println!("{}", "🛫 Running Preflight Checks...".bold());
+    }
+    let mut ran_checks = false;
+    for (index, config) in cfg.preflight.iter().enumerate() {
+        if !opts.selects_entry(index, config) {
+            continue;
+        }
+        let config = opts.select_checks(config);
+        if config.checks.is_empty() {
+            continue;
         }
-    } else if let (true, Some(index)) = (cfg.over_ride, stopped_at) {
-        over_ride(cfg, index)?;
+        let hook_matches = config.run_when.contains(&opts.hook);
+        if !hook_matches && opts.hook != "preflight" {
+            continue;
+        }
+        ran_checks = true;
+        if opts.dry_run {
+            print_plan(&config, opts)?;
+            continue;
+        }
+        if !hook_matches {
+            println!("Running all defined preflight checks...");
+            println!("{:?} checks:", config.run_when);
+        }
+        preflight_checks(&config, opts, 0)?;
+    }
+    if !ran_checks && (opts.entry.is_some() || !opts.checks.is_empty()) {
+        println!(
+            "{}",
+            "No configured checks matched the given selection".italic()
+        );
     }
+    Ok(())
+}
+
+/// Run the checks configured for `publish`, then forward to `cargo publish` if they pass.
+fn publish_crate(cfg: &PreflightConfigWrapper, matches: &clap::ArgMatches) -> Result<()> {
+    let opts = RunOptions::from_hook(matches, "publish");
+    let args: Vec<&String> = matches
+        .get_many::<String>("ARGS")
+        .map(Iterator::collect)
+        .unwrap_or_default();
+    let mut cmd = Command::new("cargo");
+    cmd.arg("publish").args(args);
 
+    run_preflight(cfg, &opts)?;
+    if opts.dry_run {
+        println!("Then: {}", describe_command(&cmd)?);
+        return Ok(());
+    }
+
+    println!("{}", "🛬 Preflight checks passed, publishing...".bold());
+    let status = cmd.status()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("`cargo publish` failed ({status})"));
+    }
     Ok(())
 }
 
@@ -43,6 +228,9 @@ pub fn preflight(matches: &clap::ArgMatches, hook: &str) -> Result<()> {
     let ground = matches.get_one::<bool>("ground");
     let configure = matches.get_one::<bool>("config");
     let checklist = matches.get_one::<bool>("checklist");
+    let run = matches.subcommand_matches("run");
+    let publish = matches.subcommand_matches("publish");
+    let secrets_baseline = matches.subcommand_matches("secrets-baseline");
     if init == Some(&true) {
         println!("Initialising...");
         init_symlink()?;
@@ -53,17 +241,14 @@ pub fn preflight(matches: &clap::ArgMatches, hook: &str) -> Result<()> {
         update_config()?;
     } else if checklist == Some(&true) {
         print_checklist()?;
+    } else if let Some(publish) = publish {
+        publish_crate(&cfg, publish)?;
+    } else if secrets_baseline.is_some() {
+        update_baseline(&cfg)?;
+    } else if let Some(run) = run {
+        run_preflight(&cfg, &RunOptions::from_matches(run, hook))?;
     } else {
-        println!("{}", "🛫 Running Preflight Checks...".bold());
-        for config in &cfg.preflight {
-            if config.run_when.contains(&hook.to_owned()) {
-                preflight_checks(config, 0)?;
-            } else if hook == "preflight" {
-                println!("Running all defined preflight checks...");
-                println!("{:?} checks:", config.run_when);
-                preflight_checks(config, 0)?;
-            }
-        }
+        run_preflight(&cfg, &RunOptions::from_hook(matches, hook))?;
     }
     Ok(())
 }
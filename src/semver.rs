@@ -0,0 +1,144 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    checks::{SEMVER_ARGS, cargo_command},
+    config::{CheckOptions, SemverConfig},
+    error::PreflightError,
+    git::latest_tag,
+};
+
+/// Git revision to compare against, falling back to the latest tag.
+fn baseline_rev(cfg: &SemverConfig) -> Option<String> {
+    cfg.baseline_rev.clone().or_else(latest_tag)
+}
+
+pub fn semver_command(options: &CheckOptions, baseline_args: &[String]) -> Command {
+    // cargo-semver-checks has no `--target`, it checks the host's rustdoc JSON
+    let options = CheckOptions {
+        target: None,
+        ..options.clone()
+    };
+    cargo_command(&SEMVER_ARGS, &options, baseline_args)
+}
+
+/// The command the `semver` check would run, without unpacking a baseline `.crate` file.
+pub fn describe_semver(cfg: &SemverConfig, options: &CheckOptions) -> Command {
+    let baseline_args = cfg.baseline_crate.as_ref().map_or_else(
+        || {
+            vec![
+                "--baseline-rev".to_owned(),
+                baseline_rev(cfg).unwrap_or_else(|| "<no baseline found>".to_owned()),
+            ]
+        },
+        |path| {
+            vec![
+                "--baseline-root".to_owned(),
+                format!("<unpacked {}>", path.display()),
+            ]
+        },
+    );
+    semver_command(options, &baseline_args)
+}
+
+/// Unpack a `.crate` file into `dir`, returning the crate's root directory.
+fn unpack_crate(path: &Path, dir: &Path) -> Result<PathBuf> {
+    let output = Command::new("tar")
+        .arg("-xzf")
+        .arg(path)
+        .arg("-C")
+        .arg(dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(PreflightError::SemverFailed {
+            semver_report: format!(
+                "Unable to unpack {}:\n{}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        }
+        .into());
+    }
+    // `.crate` files contain a single `<name>-<version>` directory
+    let root = std::fs::read_dir(dir)?
+        .next()
+        .transpose()?
+        .map_or_else(|| dir.to_owned(), |entry| entry.path());
+    Ok(root)
+}
+
+/// Summarise `cargo semver-checks` output as one entry per failed lint, with the items it failed on.
+fn report(output: &str) -> String {
+    let mut report = Vec::new();
+    let mut in_failed_items = false;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if let Some(failure) = trimmed
+            .strip_prefix("--- failure ")
+            .and_then(|failure| failure.strip_suffix(" ---"))
+        {
+            report.push(format!("    {}", failure.bold()));
+        } else if trimmed == "Failed in:" {
+            in_failed_items = true;
+        } else if trimmed.is_empty() {
+            in_failed_items = false;
+        } else if in_failed_items {
+            report.push(format!("        - {trimmed}"));
+        } else if trimmed.starts_with("Summary") {
+            report.push(trimmed.to_owned());
+        }
+    }
+    report.join("\n")
+}
+
+pub fn semver(cfg: &SemverConfig, options: &CheckOptions) -> Result<()> {
+    let installed = Command::new("cargo")
+        .args(["semver-checks", "--version"])
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !installed {
+        return Err(PreflightError::SemverFailed {
+            semver_report:
+                "`cargo-semver-checks` is not installed, install it with `cargo install cargo-semver-checks`"
+                    .to_owned(),
+        }
+        .into());
+    }
+
+    let unpacked = tempfile::tempdir()?;
+    let baseline_args = if let Some(path) = &cfg.baseline_crate {
+        vec![
+            "--baseline-root".to_owned(),
+            unpack_crate(path, unpacked.path())?.display().to_string(),
+        ]
+    } else {
+        let rev = baseline_rev(cfg).ok_or_else(|| PreflightError::SemverFailed {
+            semver_report:
+                "No baseline to compare against, set `baseline_rev` or `baseline_crate`, or tag a release"
+                    .to_owned(),
+        })?;
+        vec!["--baseline-rev".to_owned(), rev]
+    };
+
+    let output = semver_command(options, &baseline_args).output()?;
+
+    if output.status.success() {
+        println!("    {}", "[√] Semver preflight check passed".green());
+        Ok(())
+    } else {
+        let output = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let report = report(&output);
+        Err(PreflightError::SemverFailed {
+            semver_report: if report.is_empty() { output } else { report },
+        }
+        .into())
+    }
+}
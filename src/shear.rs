@@ -0,0 +1,442 @@
+use anyhow::Result;
+use cargo_metadata::{Metadata, MetadataCommand};
+use cargo_shear::{CargoShear, cargo_shear_options};
+use colored::Colorize;
+use git2::Patch;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    io::Read,
+    path::PathBuf,
+    process::ExitCode,
+};
+use tabled::{builder::Builder, settings::Style};
+use toml_edit::{DocumentMut, Item};
+
+use crate::{config::ShearConfig, error::PreflightError};
+
+/// Unused dependencies cargo-shear found in one manifest.
+struct ShearFinding {
+    /// Package name, or `root` for the workspace manifest
+    package: String,
+    manifest: String,
+    dependencies: Vec<String>,
+}
+
+/// cargo-shear flags for the configured packages and macro expansion.
+fn shear_flags(cfg: &ShearConfig) -> Vec<String> {
+    let mut flags = Vec::new();
+    for package in &cfg.packages {
+        flags.extend(["--package".to_owned(), package.clone()]);
+    }
+    for package in &cfg.exclude {
+        flags.extend(["--exclude".to_owned(), package.clone()]);
+    }
+    if cfg.expand {
+        flags.push("--expand".to_owned());
+    }
+    flags
+}
+
+/// Run cargo-shear in-process on the current directory, returning its exit code and output.
+fn run_shear(flags: &[String]) -> Result<(ExitCode, String)> {
+    let args: Vec<OsString> = flags
+        .iter()
+        .map(OsString::from)
+        .chain([env::current_dir()?.into_os_string()])
+        .collect();
+    let args: Vec<&OsStr> = args.iter().map(OsString::as_os_str).collect();
+    let options = cargo_shear_options()
+        .run_inner(args.as_slice())
+        .map_err(|_| anyhow::anyhow!("Parse failure"))?;
+    let mut buf = gag::BufferRedirect::stdout()?;
+    let exit_code = CargoShear::new(options).run();
+    let mut output = String::new();
+    buf.read_to_string(&mut output)?;
+    drop(buf);
+    Ok((exit_code, output))
+}
+
+/// Findings from cargo-shear's output, a `<package> -- <manifest>:` line followed by indented dependencies.
+fn parse_findings(output: &str) -> Vec<ShearFinding> {
+    let mut findings: Vec<ShearFinding> = Vec::new();
+    for line in output.lines() {
+        if let Some(dependency) = line.strip_prefix("  ") {
+            if let Some(finding) = findings.last_mut() {
+                finding.dependencies.push(dependency.trim().to_owned());
+            }
+        } else if let Some((package, manifest)) = line
+            .strip_suffix(':')
+            .and_then(|line| line.split_once(" -- "))
+        {
+            findings.push(ShearFinding {
+                package: package.to_owned(),
+                manifest: manifest.to_owned(),
+                dependencies: vec![],
+            });
+        }
+    }
+    for finding in &mut findings {
+        finding.dependencies.sort();
+    }
+    findings
+}
+
+/// Names of the dependencies of packages cargo-shear doesn't check, as configured or in `skipped`.
+fn excluded_dependencies(
+    metadata: &Metadata,
+    cfg: &ShearConfig,
+    skipped: &[String],
+) -> BTreeSet<String> {
+    metadata
+        .workspace_packages()
+        .into_iter()
+        .filter(|package| {
+            let name = package.name.to_string();
+            cfg.exclude.contains(&name)
+                || skipped.contains(&name)
+                || (!cfg.packages.is_empty() && !cfg.packages.contains(&name))
+        })
+        .flat_map(|package| &package.dependencies)
+        .flat_map(|dependency| [Some(dependency.name.clone()), dependency.rename.clone()])
+        .flatten()
+        .collect()
+}
+
+/// Findings without the workspace dependencies that `excluded` packages use.
+///
+/// cargo-shear doesn't look at excluded packages, so reports what only they use as unused in the workspace manifest.
+fn without_excluded(findings: Vec<ShearFinding>, excluded: &BTreeSet<String>) -> Vec<ShearFinding> {
+    findings
+        .into_iter()
+        .filter_map(|mut finding| {
+            if finding.package == "root" {
+                finding
+                    .dependencies
+                    .retain(|dependency| !excluded.contains(dependency));
+            }
+            (!finding.dependencies.is_empty()).then_some(finding)
+        })
+        .collect()
+}
+
+/// Findings left after removing ignored dependencies.
+fn reported_findings(cfg: &ShearConfig, findings: Vec<ShearFinding>) -> Vec<ShearFinding> {
+    findings
+        .into_iter()
+        .filter_map(|mut finding| {
+            finding
+                .dependencies
+                .retain(|dependency| !cfg.ignored.contains(dependency));
+            (!finding.dependencies.is_empty()).then_some(finding)
+        })
+        .collect()
+}
+
+fn findings_table(findings: &[ShearFinding]) -> String {
+    let mut builder = Builder::new();
+    builder.push_record(["Package", "Manifest", "Unused dependencies"]);
+    for finding in findings {
+        builder.push_record([
+            finding.package.clone(),
+            finding.manifest.clone(),
+            finding.dependencies.join("\n"),
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::extended());
+    table.to_string()
+}
+
+pub fn shear(cfg: &ShearConfig) -> Result<()> {
+    let (exit_code, output) = run_shear(&shear_flags(cfg))?;
+    if exit_code == ExitCode::from(2) {
+        return Err(PreflightError::ShearFailed {
+            shear_output: output,
+        }
+        .into());
+    }
+
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let findings = reported_findings(
+        cfg,
+        without_excluded(
+            parse_findings(&output),
+            &excluded_dependencies(&metadata, cfg, &[]),
+        ),
+    );
+    if findings.is_empty() {
+        println!(
+            "{}",
+            "    [√] Unused dependencies preflight check passed".green()
+        );
+        Ok(())
+    } else {
+        Err(PreflightError::ShearFailed {
+            shear_output: format!(
+                "{}\nIf a dependency is used in a way cargo-shear can't see, add it to `ignored` under `[preflight.shear]`",
+                findings_table(&findings)
+            ),
+        }
+        .into())
+    }
+}
+
+/// Contents of the workspace's manifests, by path.
+fn read_manifests(metadata: &Metadata) -> Result<BTreeMap<PathBuf, String>> {
+    metadata
+        .workspace_packages()
+        .into_iter()
+        .map(|package| package.manifest_path.clone())
+        .chain([metadata.workspace_root.join("Cargo.toml")])
+        .map(|path| {
+            let path = path.into_std_path_buf();
+            let contents = fs::read_to_string(&path)?;
+            Ok((path, contents))
+        })
+        .collect()
+}
+
+/// A diff of the changes made to manifests since they were read with `read_manifests`.
+///
+/// Only changes made since then are shown, rather than all uncommitted changes to the manifests.
+fn manifest_diff(before: &BTreeMap<PathBuf, String>) -> Result<String> {
+    let root = env::current_dir()?;
+    let mut diff = Vec::new();
+    for (path, old) in before {
+        let new = fs::read_to_string(path)?;
+        if new == *old {
+            continue;
+        }
+        let label = path.strip_prefix(&root).unwrap_or(path);
+        let mut changes = Patch::from_buffers(
+            old.as_bytes(),
+            Some(label),
+            new.as_bytes(),
+            Some(label),
+            None,
+        )?;
+        diff.extend(
+            String::from_utf8_lossy(&changes.to_buf()?)
+                .lines()
+                .map(|line| match line.chars().next() {
+                    Some('+') if !line.starts_with("+++") => line.green().to_string(),
+                    Some('-') if !line.starts_with("---") => line.red().to_string(),
+                    Some('@') => line.cyan().to_string(),
+                    _ => line.bold().to_string(),
+                }),
+        );
+    }
+    Ok(diff.join("\n"))
+}
+
+/// The workspace manifest `after` fix mode, with the workspace dependencies in `keep` that it removed put back from
+/// `before`, or `None` if none were removed.
+fn restore_workspace_dependencies(
+    before: &str,
+    after: &str,
+    keep: &BTreeSet<String>,
+) -> Result<Option<String>> {
+    let before: DocumentMut = before.parse()?;
+    let mut after: DocumentMut = after.parse()?;
+    let Some(dependencies) = before
+        .get("workspace")
+        .and_then(|workspace| workspace.get("dependencies"))
+        .and_then(Item::as_table_like)
+    else {
+        return Ok(None);
+    };
+
+    let mut restored = false;
+    for (name, dependency) in dependencies.iter().filter(|(name, _)| keep.contains(*name)) {
+        let Some(remaining) = after["workspace"]["dependencies"].as_table_like_mut() else {
+            continue;
+        };
+        if !remaining.contains_key(name) {
+            remaining.insert(name, dependency.clone());
+            restored = true;
+        }
+    }
+    Ok(restored.then(|| after.to_string()))
+}
+
+/// Remove unused dependencies with cargo-shear's fix mode, then show the changes to manifests.
+///
+/// Packages with ignored unused dependencies are excluded, since fix mode would remove those too. Fix mode also removes
+/// workspace dependencies that are ignored or only used by excluded packages, so those are put back.
+pub fn fix_shear(cfg: &ShearConfig) -> Result<()> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let mut flags = shear_flags(cfg);
+    let (_, output) = run_shear(&flags)?;
+    let (skipped, _): (Vec<ShearFinding>, _) =
+        parse_findings(&output).into_iter().partition(|finding| {
+            finding.package != "root"
+                && finding
+                    .dependencies
+                    .iter()
+                    .any(|dependency| cfg.ignored.contains(dependency))
+        });
+    let skipped_packages: Vec<String> = skipped
+        .iter()
+        .map(|finding| finding.package.clone())
+        .collect();
+    for package in &skipped_packages {
+        flags.extend(["--exclude".to_owned(), package.clone()]);
+    }
+
+    flags.push("--fix".to_owned());
+    let manifests = read_manifests(&metadata)?;
+    let (exit_code, output) = run_shear(&flags)?;
+    // In fix mode, cargo-shear exits with 1 once it has fixed something
+    if exit_code == ExitCode::from(2) {
+        return Err(PreflightError::ShearFailed {
+            shear_output: output,
+        }
+        .into());
+    }
+
+    let root = metadata
+        .workspace_root
+        .join("Cargo.toml")
+        .into_std_path_buf();
+    let mut keep = excluded_dependencies(&metadata, cfg, &skipped_packages);
+    keep.extend(cfg.ignored.iter().cloned());
+    if let Some(restored) =
+        restore_workspace_dependencies(&manifests[&root], &fs::read_to_string(&root)?, &keep)?
+    {
+        fs::write(&root, restored)?;
+    }
+
+    println!("{}", manifest_diff(&manifests)?);
+    println!(
+        "    {}",
+        "[√] Removing unused dependencies successful".yellow()
+    );
+
+    // Rerunning the check would fail again on these, so report them rather than looping
+    let remaining = reported_findings(cfg, skipped);
+    if remaining.is_empty() {
+        Ok(())
+    } else {
+        Err(PreflightError::ShearFailed {
+            shear_output: format!(
+                "{}\nThese packages also have ignored dependencies, so remove the others by hand",
+                findings_table(&remaining)
+            ),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "Analyzing /tmp/ws
+
+b -- b/Cargo.toml:
+  serde
+  anyhow
+
+root -- Cargo.toml:
+  a
+  b
+
+
+cargo-shear may have detected unused dependencies incorrectly due to its limitations.
+They can be ignored by adding the crate name to the package's Cargo.toml:
+
+[package.metadata.cargo-shear]
+ignored = [\"crate-name\"]
+
+or in the workspace Cargo.toml:
+
+[workspace.metadata.cargo-shear]
+ignored = [\"crate-name\"]
+";
+
+    fn summary(findings: &[ShearFinding]) -> Vec<(&str, &str, Vec<&str>)> {
+        findings
+            .iter()
+            .map(|finding| {
+                (
+                    finding.package.as_str(),
+                    finding.manifest.as_str(),
+                    finding.dependencies.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_findings() {
+        assert_eq!(
+            summary(&parse_findings(OUTPUT)),
+            [
+                ("b", "b/Cargo.toml", vec!["anyhow", "serde"]),
+                ("root", "Cargo.toml", vec!["a", "b"]),
+            ]
+        );
+        assert!(parse_findings("Analyzing /tmp/ws\n\nNo unused dependencies!\n").is_empty());
+    }
+
+    #[test]
+    fn ignored_dependencies_are_not_reported() {
+        let cfg = ShearConfig {
+            ignored: vec!["serde".to_owned(), "anyhow".to_owned(), "a".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            summary(&reported_findings(&cfg, parse_findings(OUTPUT))),
+            [("root", "Cargo.toml", vec!["b"])]
+        );
+    }
+
+    #[test]
+    fn workspace_dependencies_of_excluded_packages_are_not_reported() {
+        let excluded = BTreeSet::from(["b".to_owned(), "serde".to_owned()]);
+        assert_eq!(
+            summary(&without_excluded(parse_findings(OUTPUT), &excluded)),
+            [
+                ("b", "b/Cargo.toml", vec!["anyhow", "serde"]),
+                ("root", "Cargo.toml", vec!["a"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn restores_kept_workspace_dependencies() {
+        let before = "[workspace]\nmembers = [\"a\", \"b\"]\n\n[workspace.dependencies]\na = { path = \"a\" }\nb = { path = \"b\" }\nserde = \"1\"\n";
+        let after =
+            "[workspace]\nmembers = [\"a\", \"b\"]\n\n[workspace.dependencies]\nserde = \"1\"\n";
+        let keep = BTreeSet::from(["b".to_owned(), "serde".to_owned()]);
+        let restored: DocumentMut = restore_workspace_dependencies(before, after, &keep)
+            .expect("manifests are valid TOML")
+            .expect("b was removed")
+            .parse()
+            .expect("restored manifest is valid TOML");
+        let dependencies = restored["workspace"]["dependencies"]
+            .as_table_like()
+            .expect("workspace dependencies are a table");
+        assert_eq!(
+            dependencies
+                .iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            ["serde", "b"]
+        );
+
+        assert!(
+            restore_workspace_dependencies(before, before, &keep)
+                .expect("manifests are valid TOML")
+                .is_none()
+        );
+        assert!(
+            restore_workspace_dependencies("[package]\nname = \"a\"\n", after, &keep)
+                .expect("manifests are valid TOML")
+                .is_none()
+        );
+    }
+}
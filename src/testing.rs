@@ -0,0 +1,521 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::Value;
+use std::process::Command;
+
+use crate::{
+    checks::{CargoArgs, TEST_ARGS, cargo_command},
+    config::{CheckOptions, TestConfig, TestRunner},
+    error::PreflightError,
+    impact::impacted_tests,
+};
+
+const NEXTEST_ARGS: CargoArgs = CargoArgs {
+    args: &[
+        "nextest",
+        "run",
+        "--no-fail-fast",
+        "--message-format",
+        "libtest-json",
+    ],
+    lint_args: &[],
+};
+
+/// A failed test, with the output it captured.
+struct TestFailure {
+    /// Test binary, only known when running with nextest
+    binary: Option<String>,
+    /// Test path, such as `tests::it_works`
+    name: String,
+    output: String,
+}
+
+impl TestFailure {
+    fn label(&self) -> String {
+        self.binary.as_ref().map_or_else(
+            || self.name.clone(),
+            |binary| format!("{binary} {}", self.name),
+        )
+    }
+}
+
+fn nextest_installed() -> bool {
+    Command::new("cargo")
+        .args(["nextest", "--version"])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether the `test` check runs with nextest, which it only does if nextest is configured and installed.
+fn uses_nextest(cfg: &TestConfig) -> bool {
+    cfg.runner == TestRunner::Nextest && nextest_installed()
+}
+
+/// The command the `test` check runs for a `selection` of packages and tests, using nextest if configured and installed.
+pub fn test_command(cfg: &TestConfig, options: &CheckOptions, selection: &[String]) -> Command {
+    if !uses_nextest(cfg) {
+        return cargo_command(&TEST_ARGS, options, selection);
+    }
+
+    nextest_command(cfg, options, selection)
+}
+
+/// Package and test arguments for each test run, a single run of the whole suite unless impact mode applies.
+pub fn test_selections(cfg: &TestConfig, hook: &str) -> Result<Vec<Vec<String>>> {
+    if cfg.impact && hook == "commit" {
+        Ok(impacted_tests()?.unwrap_or_else(|| vec![vec![]]))
+    } else {
+        Ok(vec![vec![]])
+    }
+}
+
+fn nextest_command(cfg: &TestConfig, options: &CheckOptions, filter_args: &[String]) -> Command {
+    let mut extra_args = Vec::new();
+    if let Some(profile) = &cfg.profile {
+        extra_args.extend(["--profile".to_owned(), profile.clone()]);
+    }
+    extra_args.extend_from_slice(filter_args);
+    let mut cmd = cargo_command(&NEXTEST_ARGS, options, &extra_args);
+    cmd.env("NEXTEST_EXPERIMENTAL_LIBTEST_JSON", "1");
+    cmd
+}
+
+/// The nextest filter expression matching only the test that failed.
+fn nextest_filter(failure: &TestFailure) -> String {
+    let filter = format!("test(={})", failure.name);
+    failure.binary.as_ref().map_or_else(
+        || filter.clone(),
+        |binary| format!("binary_id(={binary}) & {filter}"),
+    )
+}
+
+/// Options with `args` added to the test binary arguments, which go after `--` along with any configured for the check.
+fn with_test_args(options: &CheckOptions, args: impl IntoIterator<Item = String>) -> CheckOptions {
+    let mut lint_args = options.lint_args.clone().unwrap_or_default();
+    lint_args.extend(args);
+    CheckOptions {
+        lint_args: Some(lint_args),
+        ..options.clone()
+    }
+}
+
+/// A command running only the test that failed, to retry it.
+fn retry_command(
+    cfg: &TestConfig,
+    options: &CheckOptions,
+    selection: &[String],
+    failure: &TestFailure,
+    nextest: bool,
+) -> Command {
+    if nextest {
+        let mut args = selection.to_vec();
+        args.extend(["-E".to_owned(), nextest_filter(failure)]);
+        return nextest_command(cfg, options, &args);
+    }
+
+    let options = with_test_args(options, ["--exact".to_owned(), failure.name.clone()]);
+    cargo_command(&TEST_ARGS, &options, selection)
+}
+
+/// A command running every test but the `skipped` ones, to confirm nothing else failed.
+fn skip_command(
+    cfg: &TestConfig,
+    options: &CheckOptions,
+    selection: &[String],
+    skipped: &[&TestFailure],
+    nextest: bool,
+) -> Command {
+    if nextest {
+        let filters: Vec<String> = skipped
+            .iter()
+            .map(|failure| format!("({})", nextest_filter(failure)))
+            .collect();
+        let mut args = selection.to_vec();
+        args.extend(["-E".to_owned(), format!("not ({})", filters.join(" | "))]);
+        return nextest_command(cfg, options, &args);
+    }
+
+    let mut args = vec!["--exact".to_owned()];
+    for failure in skipped {
+        args.extend(["--skip".to_owned(), failure.name.clone()]);
+    }
+    cargo_command(&TEST_ARGS, &with_test_args(options, args), selection)
+}
+
+/// Whether a failed test passes within `retries` reruns.
+fn passes_on_retry(
+    cfg: &TestConfig,
+    options: &CheckOptions,
+    selection: &[String],
+    failure: &TestFailure,
+    nextest: bool,
+) -> Result<bool> {
+    for _ in 0..cfg.retries {
+        if retry_command(cfg, options, selection, failure, nextest)
+            .output()?
+            .status
+            .success()
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Failed tests from `cargo test` output, taking their output from the `---- <name> stdout ----` sections.
+fn libtest_failures(stdout: &str) -> Vec<TestFailure> {
+    let mut failures: Vec<TestFailure> = stdout
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("test ")?
+                .strip_suffix(" ... FAILED")
+                .map(|name| TestFailure {
+                    binary: None,
+                    name: name.to_owned(),
+                    output: String::new(),
+                })
+        })
+        .collect();
+
+    let mut current: Option<usize> = None;
+    for line in stdout.lines() {
+        if let Some(name) = line
+            .strip_prefix("---- ")
+            .and_then(|line| line.strip_suffix(" stdout ----"))
+        {
+            current = failures.iter().position(|failure| failure.name == name);
+        } else if line == "failures:" {
+            current = None;
+        } else if let Some(index) = current {
+            failures[index].output.push_str(line);
+            failures[index].output.push('\n');
+        }
+    }
+    failures
+}
+
+/// Failed tests from nextest's libtest-json output, named `<binary>$<test>`.
+fn nextest_failures(stdout: &str) -> Vec<TestFailure> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|event| event["type"] == "test" && event["event"] == "failed")
+        .map(|event| {
+            let name = event["name"].as_str().unwrap_or_default();
+            let (binary, name) = name.split_once('$').map_or((None, name), |(binary, name)| {
+                (Some(binary.to_owned()), name)
+            });
+            TestFailure {
+                binary,
+                name: name.to_owned(),
+                output: event["stdout"].as_str().unwrap_or_default().to_owned(),
+            }
+        })
+        .collect()
+}
+
+/// Failed tests listed by name, with their indented output.
+fn format_failures(failures: &[TestFailure]) -> String {
+    failures
+        .iter()
+        .map(|failure| {
+            let output = failure
+                .output
+                .trim()
+                .lines()
+                .map(|line| {
+                    if line.is_empty() {
+                        String::new()
+                    } else {
+                        format!("        {line}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("    {}\n{output}", failure.label().bold())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Flaky and quarantined tests, which are reported without failing the check.
+fn format_notes(flaky: &[TestFailure], quarantined: &[TestFailure]) -> String {
+    let mut notes = Vec::new();
+    if !flaky.is_empty() {
+        notes.push(format!(
+            "    {}\n{}",
+            "Flaky, passed on retry:".yellow(),
+            flaky
+                .iter()
+                .map(|failure| format!("        - {}", failure.label()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    if !quarantined.is_empty() {
+        notes.push(format!(
+            "    {}\n{}",
+            "Quarantined, failures ignored:".yellow(),
+            format_failures(quarantined)
+        ));
+    }
+    notes.join("\n")
+}
+
+pub fn test(cfg: &TestConfig, options: &CheckOptions, hook: &str) -> Result<()> {
+    let nextest = uses_nextest(cfg);
+    if cfg.runner == TestRunner::Nextest && !nextest {
+        println!(
+            "    {}",
+            "nextest isn't installed, running `cargo test` instead".italic()
+        );
+    }
+    let selections = test_selections(cfg, hook)?;
+    if selections.is_empty() {
+        println!(
+            "{}",
+            "    [√] Tests preflight check passed (no staged changes affect tests)".green()
+        );
+        return Ok(());
+    }
+
+    let mut flaky = Vec::new();
+    let mut quarantined = Vec::new();
+    let mut failed = Vec::new();
+    for selection in &selections {
+        if !selection.is_empty() {
+            println!("    Testing {}", selection.join(" ").italic());
+        }
+        let output = test_command(cfg, options, selection).output()?;
+        if output.status.success() {
+            continue;
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let failures = if nextest {
+            nextest_failures(&stdout)
+        } else {
+            libtest_failures(&stdout)
+        };
+        // Without any failed tests, the tests didn't build or run, so show everything
+        if failures.is_empty() {
+            return Err(PreflightError::TestsFailed {
+                test_outputs: format!("{stdout}{}", String::from_utf8_lossy(&output.stderr)),
+            }
+            .into());
+        }
+
+        let (flaky_start, quarantined_start, failed_start) =
+            (flaky.len(), quarantined.len(), failed.len());
+        for failure in failures {
+            if passes_on_retry(cfg, options, selection, &failure, nextest)? {
+                flaky.push(failure);
+            } else if cfg.quarantine.contains(&failure.name) {
+                quarantined.push(failure);
+            } else {
+                failed.push(failure);
+            }
+        }
+
+        // Crashes and build failures don't report a failed test, so check the run passes without the ones let through
+        if failed.len() == failed_start {
+            let skipped: Vec<&TestFailure> = flaky[flaky_start..]
+                .iter()
+                .chain(&quarantined[quarantined_start..])
+                .collect();
+            let output = skip_command(cfg, options, selection, &skipped, nextest).output()?;
+            if !output.status.success() {
+                return Err(PreflightError::TestsFailed {
+                    test_outputs: format!(
+                        "Tests failed without flaky and quarantined tests:\n{}{}",
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&output.stderr)
+                    ),
+                }
+                .into());
+            }
+        }
+    }
+
+    let notes = format_notes(&flaky, &quarantined);
+    if failed.is_empty() {
+        if notes.is_empty() {
+            println!("{}", "    [√] Tests preflight check passed".green());
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "    [√] Tests preflight check passed ({} flaky, {} quarantined)",
+                    flaky.len(),
+                    quarantined.len()
+                )
+                .green()
+            );
+            println!("{notes}");
+        }
+        Ok(())
+    } else {
+        let mut test_outputs = format_failures(&failed);
+        if !notes.is_empty() {
+            test_outputs = format!("{test_outputs}\n{notes}");
+        }
+        Err(PreflightError::TestsFailed { test_outputs }.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(failures: &[TestFailure]) -> Vec<(Option<&str>, &str, &str)> {
+        failures
+            .iter()
+            .map(|failure| {
+                (
+                    failure.binary.as_deref(),
+                    failure.name.as_str(),
+                    failure.output.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_libtest_failures() {
+        let stdout = "
+running 3 tests
+test tests::passes ... ok
+test tests::fails ... FAILED
+test tests::panics ... FAILED
+
+failures:
+
+---- tests::fails stdout ----
+left was 1
+
+thread 'tests::fails' panicked at src/lib.rs:13:9:
+assertion `left == right` failed
+
+---- tests::panics stdout ----
+thread 'tests::panics' panicked at src/lib.rs:18:9:
+explicit panic
+
+failures:
+    tests::fails
+    tests::panics
+
+test result: FAILED. 1 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+        assert_eq!(
+            summary(&libtest_failures(stdout)),
+            [
+                (
+                    None,
+                    "tests::fails",
+                    "left was 1\n\nthread 'tests::fails' panicked at src/lib.rs:13:9:\nassertion `left == right` failed\n\n"
+                ),
+                (
+                    None,
+                    "tests::panics",
+                    "thread 'tests::panics' panicked at src/lib.rs:18:9:\nexplicit panic\n\n"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn passing_libtest_output_has_no_failures() {
+        assert!(
+            libtest_failures("running 1 test\ntest tests::passes ... ok\n\ntest result: ok.")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn parses_nextest_failures() {
+        let stdout = r#"{"type":"suite","event":"started","test_count":3,"nextest":{"crate":"app","test_binary":"app","kind":"lib"}}
+{"type":"test","event":"started","name":"app$tests::fails"}
+{"type":"test","name":"app$tests::fails","event":"failed","exec_time":0.01,"stdout":"left was 1\nassertion failed\n"}
+{"type":"test","name":"app::integration$it_works","event":"ok","exec_time":0.01}
+{"type":"test","name":"app::integration$it_breaks","event":"failed","exec_time":0.02,"stdout":"explicit panic\n"}
+{"type":"suite","event":"failed","passed":1,"failed":2,"ignored":0,"measured":0,"filtered_out":0,"exec_time":0.03}
+not json
+"#;
+        assert_eq!(
+            summary(&nextest_failures(stdout)),
+            [
+                (
+                    Some("app"),
+                    "tests::fails",
+                    "left was 1\nassertion failed\n"
+                ),
+                (Some("app::integration"), "it_breaks", "explicit panic\n"),
+            ]
+        );
+    }
+
+    fn failure(binary: Option<&str>, name: &str) -> TestFailure {
+        TestFailure {
+            binary: binary.map(ToOwned::to_owned),
+            name: name.to_owned(),
+            output: String::new(),
+        }
+    }
+
+    fn command_args(cmd: &Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn skips_let_through_tests() {
+        let (flaky, quarantined) = (
+            failure(Some("app"), "tests::flaky"),
+            failure(None, "tests::quarantined"),
+        );
+        let options = CheckOptions::default();
+        let cfg = TestConfig::default();
+        let selection = ["-p".to_owned(), "app".to_owned()];
+
+        let cmd = skip_command(&cfg, &options, &selection, &[&flaky, &quarantined], false);
+        let args = command_args(&cmd);
+        assert!(args.starts_with(&["test".to_owned(), "--no-fail-fast".to_owned()]));
+        assert!(
+            args.ends_with(
+                &[
+                    "-p",
+                    "app",
+                    "--",
+                    "--exact",
+                    "--skip",
+                    "tests::flaky",
+                    "--skip",
+                    "tests::quarantined"
+                ]
+                .map(ToOwned::to_owned)
+            )
+        );
+
+        let cmd = skip_command(&cfg, &options, &selection, &[&flaky, &quarantined], true);
+        assert!(
+            command_args(&cmd).ends_with(
+                &[
+                    "-p",
+                    "app",
+                    "-E",
+                    "not ((binary_id(=app) & test(=tests::flaky)) | (test(=tests::quarantined)))"
+                ]
+                .map(ToOwned::to_owned)
+            )
+        );
+    }
+
+    #[test]
+    fn nextest_failures_without_a_binary_keep_their_name() {
+        let failures =
+            nextest_failures(r#"{"type":"test","name":"tests::fails","event":"failed"}"#);
+        assert_eq!(summary(&failures), [(None, "tests::fails", "")]);
+        assert_eq!(failures[0].label(), "tests::fails");
+    }
+}
@@ -0,0 +1,381 @@
+use anyhow::Result;
+use cargo_metadata::{MetadataCommand, semver::Version};
+use colored::Colorize;
+use std::process::Command;
+use tabled::{builder::Builder, settings::Style};
+
+use crate::{
+    checks::{MSRV_ARGS, cargo_command},
+    config::CheckOptions,
+    error::PreflightError,
+};
+
+const RUSTUP_REQUIRED: &str =
+    "rustup is required to find installed toolchains, install it from https://rustup.rs";
+
+/// Names of the toolchains installed with rustup, such as `stable-x86_64-unknown-linux-gnu`.
+///
+/// Fails with `RUSTUP_REQUIRED` if rustup isn't installed.
+fn installed_toolchains() -> Result<Vec<String>, &'static str> {
+    let output = Command::new("rustup")
+        .args(["toolchain", "list"])
+        .output()
+        .map_err(|_| RUSTUP_REQUIRED)?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+/// Targets installed for `toolchain`, or for the default toolchain if `None`.
+fn installed_targets(toolchain: Option<&str>) -> Vec<String> {
+    let mut cmd = Command::new("rustup");
+    if let Some(toolchain) = toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.args(["target", "list", "--installed"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(ToOwned::to_owned)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether `toolchain` (such as `stable` or `1.75`) matches an installed toolchain name.
+fn is_installed(toolchain: &str, installed: &[String]) -> bool {
+    installed
+        .iter()
+        .any(|name| name == toolchain || name.starts_with(&format!("{toolchain}-")))
+}
+
+/// The options for one run in a check's matrix, and the commands to run for it.
+pub type MatrixRun = (CheckOptions, Vec<Command>);
+
+/// `items` in order, without repeats.
+fn unique<T: PartialEq>(items: impl IntoIterator<Item = T>) -> Vec<T> {
+    items.into_iter().fold(Vec::new(), |mut unique, item| {
+        if !unique.contains(&item) {
+            unique.push(item);
+        }
+        unique
+    })
+}
+
+/// The options for each run in a check's matrix, one per toolchain and target, grouped by toolchain.
+pub fn matrix_cells(options: &CheckOptions) -> Vec<CheckOptions> {
+    let toolchains = if options.toolchains.is_empty() {
+        vec![options.toolchain.clone()]
+    } else {
+        unique(options.toolchains.iter().cloned().map(Some))
+    };
+    let targets = if options.targets.is_empty() {
+        vec![options.target.clone()]
+    } else {
+        unique(options.targets.iter().cloned().map(Some))
+    };
+
+    toolchains
+        .iter()
+        .flat_map(|toolchain| {
+            targets.iter().map(|target| CheckOptions {
+                toolchain: toolchain.clone(),
+                target: target.clone(),
+                toolchains: vec![],
+                targets: vec![],
+                ..options.clone()
+            })
+        })
+        .collect()
+}
+
+/// A table of each run's result, with a row per toolchain and a column per target.
+fn matrix_table(check: &str, results: &[(CheckOptions, String)]) -> String {
+    let targets = unique(results.iter().map(|(cell, _)| cell.target.clone()));
+    let mut builder = Builder::new();
+    builder.push_record(
+        std::iter::once(check.to_owned()).chain(
+            targets
+                .iter()
+                .map(|target| target.clone().unwrap_or_else(|| "default".to_owned())),
+        ),
+    );
+    for row in results.chunks(targets.len().max(1)) {
+        let toolchain = row[0]
+            .0
+            .toolchain
+            .clone()
+            .unwrap_or_else(|| "default".to_owned());
+        builder.push_record(
+            std::iter::once(toolchain).chain(row.iter().map(|(_, result)| result.clone())),
+        );
+    }
+    let mut table = builder.build();
+    table.with(Style::extended());
+    table.to_string()
+}
+
+/// Run `check` once per toolchain and target, with the commands for each cell from `matrix_cells`, and show the results as a table.
+pub fn matrix(check: &str, runs: Vec<MatrixRun>) -> Result<()> {
+    let installed = installed_toolchains().map_err(|err| PreflightError::MatrixFailed {
+        check: check.to_owned(),
+        matrix_output: err.to_owned(),
+    })?;
+
+    let mut failures = Vec::new();
+    let mut results = Vec::new();
+    for (cell, commands) in runs {
+        let toolchain = cell.toolchain.as_deref();
+        let label = format!(
+            "{} {}",
+            toolchain.unwrap_or("default"),
+            cell.target.as_deref().unwrap_or("default")
+        );
+        let missing = if toolchain.is_some_and(|toolchain| !is_installed(toolchain, &installed)) {
+            Some("toolchain not installed")
+        } else if cell
+            .target
+            .as_ref()
+            .is_some_and(|target| !installed_targets(toolchain).contains(target))
+        {
+            Some("target not installed")
+        } else {
+            None
+        };
+
+        let result = if let Some(missing) = missing {
+            failures.push(format!("    {}\n        {missing}", label.bold()));
+            missing.yellow().to_string()
+        } else {
+            let mut passed = true;
+            for mut cmd in commands {
+                let output = cmd.output()?;
+                if !output.status.success() {
+                    passed = false;
+                    failures.push(format!(
+                        "    {}\n{}{}",
+                        label.bold(),
+                        String::from_utf8_lossy(&output.stdout),
+                        String::from_utf8_lossy(&output.stderr)
+                    ));
+                }
+            }
+            if passed {
+                "[√]".green().to_string()
+            } else {
+                "[x]".red().to_string()
+            }
+        };
+        results.push((cell, result));
+    }
+    println!("{}", matrix_table(check, &results));
+
+    if failures.is_empty() {
+        println!(
+            "    {}",
+            format!("[√] {check} matrix preflight check passed").green()
+        );
+        Ok(())
+    } else {
+        Err(PreflightError::MatrixFailed {
+            check: check.to_owned(),
+            matrix_output: failures.join("\n"),
+        }
+        .into())
+    }
+}
+
+/// The installed toolchain best matching `version`, preferring an exact patch version.
+fn find_toolchain(version: &Version, installed: &[String]) -> Option<String> {
+    let exact = format!("{}.{}.{}", version.major, version.minor, version.patch);
+    let minor = format!("{}.{}", version.major, version.minor);
+    let channel = |toolchain: &String| {
+        toolchain
+            .split_once('-')
+            .map_or_else(|| toolchain.clone(), |(channel, _)| channel.to_owned())
+    };
+
+    installed
+        .iter()
+        .find(|toolchain| channel(toolchain) == exact)
+        .or_else(|| {
+            installed
+                .iter()
+                .find(|toolchain| channel(toolchain) == minor)
+        })
+        .or_else(|| {
+            installed
+                .iter()
+                .find(|toolchain| channel(toolchain).starts_with(&format!("{minor}.")))
+        })
+        .map(channel)
+}
+
+/// The `cargo +<toolchain> check` commands for each workspace package with a `rust-version`.
+pub fn msrv_commands(options: &CheckOptions) -> Result<Vec<(String, Result<Command, Version>)>> {
+    let metadata = MetadataCommand::new().no_deps().exec()?;
+    let installed = installed_toolchains().map_err(|err| PreflightError::MsrvFailed {
+        msrv_output: err.to_owned(),
+    })?;
+
+    Ok(metadata
+        .workspace_packages()
+        .into_iter()
+        .filter_map(|package| {
+            let version = package.rust_version.as_ref()?;
+            let label = format!("{} (rust-version {version})", package.name);
+            let cmd = find_toolchain(version, &installed)
+                .map(|toolchain| {
+                    let options = CheckOptions {
+                        toolchain: Some(toolchain),
+                        ..options.clone()
+                    };
+                    cargo_command(
+                        &MSRV_ARGS,
+                        &options,
+                        &["-p".to_owned(), package.name.to_string()],
+                    )
+                })
+                .ok_or_else(|| version.clone());
+            Some((label, cmd))
+        })
+        .collect())
+}
+
+pub fn msrv(options: &CheckOptions) -> Result<()> {
+    let commands = msrv_commands(options)?;
+    if commands.is_empty() {
+        return Err(PreflightError::MsrvFailed {
+            msrv_output: "No workspace package sets `rust-version` in Cargo.toml".to_owned(),
+        }
+        .into());
+    }
+
+    let mut failed = Vec::new();
+    for (label, cmd) in commands {
+        match cmd {
+            Ok(mut cmd) => {
+                let output = cmd.output()?;
+                if !output.status.success() {
+                    failed.push(format!(
+                        "    {}\n{}",
+                        label.bold(),
+                        String::from_utf8_lossy(&output.stderr)
+                    ));
+                }
+            }
+            Err(version) => failed.push(format!(
+                "    {}\n        No matching toolchain is installed, install it with `rustup toolchain install {}.{}.{}`",
+                label.bold(),
+                version.major,
+                version.minor,
+                version.patch
+            )),
+        }
+    }
+
+    if failed.is_empty() {
+        println!("    {}", "[√] MSRV preflight check passed".green());
+        Ok(())
+    } else {
+        Err(PreflightError::MsrvFailed {
+            msrv_output: failed.join("\n"),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(toolchains: &[&str], targets: &[&str]) -> CheckOptions {
+        CheckOptions {
+            toolchains: toolchains.iter().map(ToString::to_string).collect(),
+            targets: targets.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn labels(cells: &[CheckOptions]) -> Vec<(Option<&str>, Option<&str>)> {
+        cells
+            .iter()
+            .map(|cell| (cell.toolchain.as_deref(), cell.target.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn cells_are_grouped_by_toolchain() {
+        let cells = matrix_cells(&options(&["stable", "1.75"], &["x86", "wasm"]));
+        assert_eq!(
+            labels(&cells),
+            [
+                (Some("stable"), Some("x86")),
+                (Some("stable"), Some("wasm")),
+                (Some("1.75"), Some("x86")),
+                (Some("1.75"), Some("wasm")),
+            ]
+        );
+        assert!(cells.iter().all(|cell| !cell.has_matrix()));
+    }
+
+    #[test]
+    fn cells_skip_repeated_toolchains_and_targets() {
+        let cells = matrix_cells(&options(&["stable", "stable"], &["x86", "wasm", "x86"]));
+        assert_eq!(
+            labels(&cells),
+            [
+                (Some("stable"), Some("x86")),
+                (Some("stable"), Some("wasm"))
+            ]
+        );
+    }
+
+    #[test]
+    fn cells_fall_back_to_single_toolchain_and_target() {
+        let cells = matrix_cells(&CheckOptions {
+            toolchain: Some("nightly".to_owned()),
+            targets: vec!["x86".to_owned(), "wasm".to_owned()],
+            ..Default::default()
+        });
+        assert_eq!(
+            labels(&cells),
+            [
+                (Some("nightly"), Some("x86")),
+                (Some("nightly"), Some("wasm"))
+            ]
+        );
+    }
+
+    #[test]
+    fn table_has_row_per_toolchain_and_column_per_target() {
+        let results: Vec<(CheckOptions, String)> =
+            matrix_cells(&options(&["stable", "1.75"], &["x86", "wasm", "x86"]))
+                .into_iter()
+                .enumerate()
+                .map(|(index, cell)| (cell, format!("result{index}")))
+                .collect();
+        let table = matrix_table("clippy", &results);
+        let rows: Vec<Vec<&str>> = table
+            .lines()
+            .filter(|line| line.starts_with('║') || line.starts_with('│'))
+            .map(|line| {
+                line.split(['║', '│'])
+                    .map(str::trim)
+                    .filter(|cell| !cell.is_empty())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                vec!["clippy", "x86", "wasm"],
+                vec!["stable", "result0", "result1"],
+                vec!["1.75", "result2", "result3"],
+            ]
+        );
+    }
+}
@@ -1,3 +1,9 @@
+use anyhow::Result;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::BTreeMap;
+
+use crate::config::CheckOptions;
+
 pub fn display_vecs(vec: &[String]) -> String {
     vec.iter()
         .map(|item| format!("- {item}"))
@@ -11,3 +17,25 @@ pub fn display_checks(vec: &[String]) -> String {
         .collect::<Vec<String>>()
         .join("\n")
 }
+
+#[allow(clippy::ref_option)] // signature required by `tabled(display)`
+pub fn display_name(name: &Option<String>) -> String {
+    name.clone().unwrap_or_default()
+}
+
+pub fn display_options(options: &BTreeMap<String, CheckOptions>) -> String {
+    options
+        .iter()
+        .map(|(check, options)| format!("{check}: {options}"))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Set of globs, to match paths against any of them.
+pub fn glob_set(globs: &[impl AsRef<str>]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob.as_ref())?);
+    }
+    Ok(builder.build()?)
+}